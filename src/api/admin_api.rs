@@ -0,0 +1,253 @@
+use std::{
+    env,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    get,
+    http::StatusCode,
+    post,
+    rt::time::timeout,
+    web::{Bytes, Data},
+    HttpResponse,
+};
+use arc_swap::ArcSwap;
+use serde::Serialize;
+
+use crate::{
+    api::{api_error::error_response, db_error::repo_error_response},
+    config::runtime_config::{RuntimeConfig, RuntimeConfigSource},
+    repository::{mongodb_repo::MongoRepo, repo_metrics::RepoMetricsSnapshot},
+};
+
+/// How long `GET /health` and `GET /readyz` wait for the database before reporting it unavailable.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Body returned by `POST /admin/config/reload`.
+#[derive(Debug, Serialize)]
+pub struct ReloadResponse {
+    /// Whether read-only mode is enabled after the reload.
+    pub read_only: bool,
+}
+
+#[post("/admin/config/reload")]
+pub async fn reload_config(
+    config: Data<ArcSwap<RuntimeConfig>>,
+    source: Data<dyn RuntimeConfigSource>,
+) -> HttpResponse {
+    let reloaded = source.load();
+    let body = ReloadResponse {
+        read_only: reloaded.read_only,
+    };
+    config.store(Arc::new(reloaded));
+
+    HttpResponse::Ok().json(body)
+}
+
+/// Body returned by `POST /admin/restore`.
+#[derive(Debug, Serialize)]
+pub struct RestoreResponse {
+    /// The number of documents inserted from the dump.
+    pub restored: usize,
+}
+
+/// Body returned by `POST /admin/reconnect`.
+#[derive(Debug, Serialize)]
+pub struct ReconnectResponse {
+    /// The database the repository targets after reconnecting.
+    pub database: String,
+}
+
+#[post("/admin/reconnect")]
+pub async fn reconnect(db: Data<MongoRepo>) -> HttpResponse {
+    let uri = match env::var("MONGOURI") {
+        Ok(uri) => uri,
+        Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "MONGOURI is not set"),
+    };
+    let result = db.reconnect(&uri).await;
+
+    match result {
+        Ok(()) => HttpResponse::Ok().json(ReconnectResponse {
+            database: db.database_name(),
+        }),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/admin/dump")]
+pub async fn dump_users(db: Data<MongoRepo>) -> HttpResponse {
+    let dump = db.dump().await;
+
+    match dump {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .body(bytes),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[post("/admin/restore")]
+pub async fn restore_users(db: Data<MongoRepo>, body: Bytes) -> HttpResponse {
+    let result = db.restore(&body).await;
+
+    match result {
+        Ok(restored) => HttpResponse::Ok().json(RestoreResponse { restored }),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+/// Body returned by `POST /admin/backfill-timestamps`.
+#[derive(Debug, Serialize)]
+pub struct BackfillResponse {
+    /// The number of users whose timestamps were filled in.
+    pub backfilled: u64,
+}
+
+#[post("/admin/backfill-timestamps")]
+pub async fn backfill_timestamps(db: Data<MongoRepo>) -> HttpResponse {
+    let result = db.backfill_timestamps().await;
+
+    match result {
+        Ok(result) => HttpResponse::Ok().json(BackfillResponse {
+            backfilled: result.modified_count,
+        }),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+/// Body returned by `GET /metrics`.
+#[derive(Debug, Serialize)]
+pub struct MetricsResponse {
+    /// Counters for the database operations the repository has performed.
+    pub repository: RepoMetricsSnapshot,
+}
+
+#[get("/metrics")]
+pub async fn get_metrics(db: Data<MongoRepo>) -> HttpResponse {
+    HttpResponse::Ok().json(MetricsResponse {
+        repository: db.metrics(),
+    })
+}
+
+/// Body returned by `GET /health`.
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    /// Always `ok`; a database that did not answer is reported as a `503` `ApiError`.
+    pub status: &'static str,
+}
+
+#[get("/health")]
+pub async fn health(db: Data<MongoRepo>) -> HttpResponse {
+    match timeout(HEALTH_TIMEOUT, db.ping()).await {
+        Ok(Ok(())) => HttpResponse::Ok().json(HealthResponse { status: "ok" }),
+        _ => error_response(StatusCode::SERVICE_UNAVAILABLE, "database unavailable"),
+    }
+}
+
+#[get("/livez")]
+pub async fn livez() -> HttpResponse {
+    HttpResponse::Ok().json(HealthResponse { status: "ok" })
+}
+
+/// Body returned by `GET /readyz`.
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    /// Always `ok`; a database that did not answer is reported as a `503` `ApiError`.
+    pub status: &'static str,
+    /// How long the database took to answer the ping, in milliseconds.
+    pub latency_ms: u64,
+}
+
+#[get("/readyz")]
+pub async fn readyz(db: Data<MongoRepo>) -> HttpResponse {
+    let started = Instant::now();
+    match timeout(HEALTH_TIMEOUT, db.ping()).await {
+        Ok(Ok(())) => HttpResponse::Ok().json(ReadyResponse {
+            status: "ok",
+            latency_ms: started.elapsed().as_millis() as u64,
+        }),
+        _ => error_response(StatusCode::SERVICE_UNAVAILABLE, "database unavailable"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::read_only::read_only;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App};
+
+    /// Source that always returns the same settings.
+    struct FixedConfigSource(RuntimeConfig);
+
+    impl RuntimeConfigSource for FixedConfigSource {
+        fn load(&self) -> RuntimeConfig {
+            self.0.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_config_enables_read_only() {
+        // Arrange
+        let config = Data::new(ArcSwap::from_pointee(RuntimeConfig::default()));
+        let source: Arc<dyn RuntimeConfigSource> = Arc::new(FixedConfigSource(RuntimeConfig {
+            read_only: true,
+            ..Default::default()
+        }));
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(read_only))
+                .app_data(config.clone())
+                .app_data(Data::from(source))
+                .service(reload_config)
+                .route("/user", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+        let before =
+            test::call_service(&app, test::TestRequest::post().uri("/user").to_request()).await;
+
+        // Act
+        let reload_req = test::TestRequest::post()
+            .uri("/admin/config/reload")
+            .to_request();
+        let reload_resp = test::call_service(&app, reload_req).await;
+        let after =
+            test::call_service(&app, test::TestRequest::post().uri("/user").to_request()).await;
+
+        // Assert
+        assert_eq!(before.status(), StatusCode::OK);
+        assert_eq!(reload_resp.status(), StatusCode::OK);
+        assert!(config.load().read_only);
+        assert_eq!(after.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_livez_does_not_need_the_database() {
+        // Arrange
+        let app = test::init_service(App::new().service(livez)).await;
+
+        // Act
+        let req = test::TestRequest::get().uri("/livez").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_reports_ping_latency() {
+        // Arrange
+        let db = Data::new(MongoRepo::init().await.unwrap());
+        let app = test::init_service(App::new().app_data(db).service(readyz)).await;
+
+        // Act
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert
+        assert_eq!(body["status"], "ok");
+        assert!(body["latency_ms"].is_u64());
+    }
+}
\ No newline at end of file
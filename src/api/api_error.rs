@@ -0,0 +1,119 @@
+use actix_web::{
+    error::{InternalError, ResponseError},
+    http::StatusCode,
+    HttpRequest, HttpResponse,
+};
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use validator::ValidationErrors;
+
+/// The messages for each invalid field of a payload.
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
+/// Body of every error response, so clients can parse failures the same way as successes.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiError {
+    /// What went wrong.
+    pub error: String,
+    /// The HTTP status code of the response.
+    pub code: u16,
+    /// The messages for each invalid field, on `422` responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<FieldErrors>,
+}
+
+/// Builds a response with `status` and an `ApiError` body carrying `message`.
+pub fn error_response(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiError {
+        error: message.into(),
+        code: status.as_u16(),
+        fields: None,
+    })
+}
+
+/// Builds a `422 Unprocessable Entity` response listing the invalid fields of a payload.
+///
+/// Each field maps to the messages of its `ValidationError`s, falling back to the error code
+/// when a rule has no message.
+pub fn validation_response(errors: ValidationErrors) -> HttpResponse {
+    let fields = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| error.message.as_ref().unwrap_or(&error.code).to_string())
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect::<FieldErrors>();
+    let status = StatusCode::UNPROCESSABLE_ENTITY;
+    HttpResponse::build(status).json(ApiError {
+        error: String::from("validation failed"),
+        code: status.as_u16(),
+        fields: Some(fields),
+    })
+}
+
+/// Turns an extractor failure, such as a malformed JSON body or query string, into an
+/// `ApiError` response with the extractor's own status code.
+pub fn extractor_error<E: ResponseError + 'static>(err: E, _req: &HttpRequest) -> actix_web::Error {
+    let resp = error_response(err.status_code(), err.to_string());
+    InternalError::from_response(err, resp).into()
+}
+
+/// Fallback for requests that match no route.
+pub async fn route_not_found() -> HttpResponse {
+    error_response(StatusCode::NOT_FOUND, "no route matches this request")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test,
+        web::{self, Json, JsonConfig},
+        App,
+    };
+
+    async fn echo(body: Json<serde_json::Value>) -> HttpResponse {
+        HttpResponse::Ok().json(body.into_inner())
+    }
+
+    #[tokio::test]
+    async fn test_errors_share_the_api_error_shape() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(JsonConfig::default().error_handler(extractor_error))
+                .route("/echo", web::post().to(echo))
+                .default_service(web::to(route_not_found)),
+        )
+        .await;
+        let malformed_req = test::TestRequest::post()
+            .uri("/echo")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{not json")
+            .to_request();
+        let missing_req = test::TestRequest::get().uri("/nowhere").to_request();
+
+        // Act
+        let malformed_resp = test::call_service(&app, malformed_req).await;
+        let malformed_status = malformed_resp.status();
+        let malformed: serde_json::Value = test::read_body_json(malformed_resp).await;
+        let missing_resp = test::call_service(&app, missing_req).await;
+        let missing_status = missing_resp.status();
+        let missing: serde_json::Value = test::read_body_json(missing_resp).await;
+
+        // Assert
+        assert_eq!(malformed_status, StatusCode::BAD_REQUEST);
+        assert_eq!(malformed["code"], 400);
+        assert!(malformed["error"].is_string());
+        assert_eq!(missing_status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            missing,
+            serde_json::json!({ "error": "no route matches this request", "code": 404 })
+        );
+    }
+}
\ No newline at end of file
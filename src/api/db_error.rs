@@ -0,0 +1,178 @@
+use actix_web::{
+    http::{
+        header::{HeaderValue, RETRY_AFTER},
+        StatusCode,
+    },
+    HttpResponse,
+};
+use mongodb::error::{Error, ErrorKind, WriteFailure, RETRYABLE_WRITE_ERROR};
+
+use crate::{api::api_error::error_response, repository::error::RepoError};
+
+/// Server error code names reported while a replica set is electing a new primary.
+const FAILOVER_CODE_NAMES: [&str; 3] = [
+    "NotWritablePrimary",
+    "PrimarySteppedDown",
+    "InterruptedDueToReplStateChange",
+];
+
+/// Seconds clients are asked to wait before retrying during a failover.
+const FAILOVER_RETRY_AFTER_SECS: u64 = 5;
+
+/// Returns `true` when `err` was caused by the primary stepping down or being unavailable.
+///
+/// Besides the failover code names, errors the server labels as retryable writes and failures
+/// to select a server (no primary reachable yet) count as failovers.
+fn is_failover(err: &Error) -> bool {
+    if err.contains_label(RETRYABLE_WRITE_ERROR) {
+        return true;
+    }
+    let code_name = match err.kind.as_ref() {
+        ErrorKind::Command(err) => &err.code_name,
+        ErrorKind::Write(WriteFailure::WriteConcernError(err)) => &err.code_name,
+        ErrorKind::ServerSelection { .. } => return true,
+        _ => return false,
+    };
+    FAILOVER_CODE_NAMES.contains(&code_name.as_str())
+}
+
+/// Translates a database error into a response.
+///
+/// Failovers become `503` with `Retry-After` so clients back off; anything else is a `500`.
+pub fn db_error_response(err: &Error) -> HttpResponse {
+    if is_failover(err) {
+        let mut resp = error_response(StatusCode::SERVICE_UNAVAILABLE, err.to_string());
+        resp.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(FAILOVER_RETRY_AFTER_SECS));
+        resp
+    } else {
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    }
+}
+
+/// Translates a repository error into a response.
+///
+/// Missing users are `404`s, duplicate emails are `409`s, malformed IDs and other invalid input
+/// are `400`s, and database failures go through `db_error_response`.
+pub fn repo_error_response(err: RepoError) -> HttpResponse {
+    match err {
+        RepoError::NotFound => {
+            error_response(StatusCode::NOT_FOUND, "No user found with specified ID")
+        }
+        RepoError::InvalidObjectId(_) => {
+            error_response(StatusCode::BAD_REQUEST, "invalid object id")
+        }
+        RepoError::DuplicateEmail => error_response(StatusCode::CONFLICT, err.to_string()),
+        RepoError::Database(err) => db_error_response(&err),
+        err => error_response(StatusCode::BAD_REQUEST, err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body::to_bytes, http::StatusCode};
+    use mongodb::{
+        bson::{doc, from_document},
+        error::{CommandError, WriteConcernError},
+    };
+
+    fn command_error(code: i32, code_name: &str) -> Error {
+        let command_error: CommandError = from_document(doc! {
+            "code": code,
+            "codeName": code_name,
+            "errmsg": "command failed",
+        })
+        .unwrap();
+        ErrorKind::Command(command_error).into()
+    }
+
+    #[test]
+    fn test_db_error_response_maps_failover_to_503() {
+        // Arrange
+        let not_primary = command_error(10107, "NotWritablePrimary");
+        let stepped_down = command_error(189, "PrimarySteppedDown");
+        let duplicate_key = command_error(11000, "DuplicateKey");
+        let custom = Error::custom("boom");
+
+        // Act
+        let not_primary_resp = db_error_response(&not_primary);
+        let stepped_down_resp = db_error_response(&stepped_down);
+
+        // Assert
+        assert_eq!(not_primary_resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(not_primary_resp.headers().contains_key(RETRY_AFTER));
+        assert_eq!(stepped_down_resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            db_error_response(&duplicate_key).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            db_error_response(&custom).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_failover_detects_labels_repl_state_changes_and_server_selection() {
+        // Arrange
+        let interrupted = command_error(11602, "InterruptedDueToReplStateChange");
+        let write_concern: WriteConcernError = from_document(doc! {
+            "code": 64,
+            "codeName": "WriteConcernFailed",
+            "errmsg": "waiting for replication timed out",
+            "errorLabels": [RETRYABLE_WRITE_ERROR],
+        })
+        .unwrap();
+        let labeled: Error =
+            ErrorKind::Write(WriteFailure::WriteConcernError(write_concern)).into();
+        let server_selection =
+            mongodb::Client::with_uri_str("mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=50")
+                .await
+                .unwrap()
+                .database("rustDB")
+                .run_command(doc! {"ping": 1}, None)
+                .await
+                .unwrap_err();
+
+        // Act
+        let interrupted_failover = is_failover(&interrupted);
+        let labeled_failover = is_failover(&labeled);
+        let server_selection_failover = is_failover(&server_selection);
+
+        // Assert
+        assert!(interrupted_failover);
+        assert!(labeled_failover);
+        assert!(server_selection_failover);
+        assert!(!is_failover(&command_error(11000, "DuplicateKey")));
+    }
+
+    #[test]
+    fn test_repo_error_response_maps_duplicate_email_to_409() {
+        // Act
+        let resp = repo_error_response(RepoError::DuplicateEmail);
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_repo_error_response_keeps_database_errors_server_side() {
+        // Arrange
+        let failover = RepoError::Database(command_error(10107, "NotWritablePrimary"));
+        let custom = RepoError::Database(Error::custom("boom"));
+
+        // Act
+        let failover_resp = repo_error_response(failover);
+        let custom_resp = repo_error_response(custom);
+        let custom_status = custom_resp.status();
+        let body = to_bytes(custom_resp.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // Assert
+        assert_eq!(failover_resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(failover_resp.headers().contains_key(RETRY_AFTER));
+        assert_eq!(custom_status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], 500);
+    }
+}
\ No newline at end of file
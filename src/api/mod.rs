@@ -1 +1,4 @@
+pub mod admin_api;
+pub mod api_error;
+pub mod db_error;
 pub mod user_api;
\ No newline at end of file
@@ -1,172 +1,2276 @@
-use crate::{models::user_model::User, repository::mongodb_repo::MongoRepo};
+use crate::{
+    api::{
+        api_error::{error_response, validation_response, ApiError},
+        db_error::repo_error_response,
+    },
+    config::{link_config::LinkConfig, user_defaults::UserDefaults},
+    events::event_sink::{EventSink, UserEvent},
+    models::user_model::{User, UserPatch, UserResponse},
+    repository::{
+        error::{parse_object_id, parse_read_concern, RepoError},
+        mongodb_repo::MongoRepo,
+    },
+};
 use actix_web::{
-    delete, get, post, put,
-    web::{Data, Json, Path},
-    HttpResponse,
+    body::to_bytes,
+    delete, get,
+    http::{
+        header::{LastModified, CONTENT_LOCATION, LOCATION},
+        StatusCode,
+    },
+    patch, post, put,
+    web::{Bytes, Data, Json, Path, Query},
+    HttpRequest, HttpResponse,
+};
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_vec, Bson, DateTime, Document},
+    options::{FindOptions, ReadConcern},
 };
-use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, env, error::Error, sync::OnceLock, time::Duration};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// Upper bound on the number of users returned by `GET /users/sample`.
+const MAX_SAMPLE_SIZE: i64 = 100;
+
+/// Upper bound on how many colleagues `GET /user/{id}/colleagues` returns.
+const MAX_COLLEAGUES: i64 = 100;
+
+/// Upper bound on the page size of `GET /users/page` and `GET /users/cursor`.
+const MAX_PAGE_SIZE: i64 = 100;
+
+/// Page size of `GET /users/page` and `GET /users/cursor` when none is given.
+const DEFAULT_PAGE_SIZE: i64 = 20;
+
+/// Upper bound on the batch size of `GET /users/scroll`.
+const MAX_SCROLL_SIZE: i64 = 1000;
+
+/// Upper bound, in serialized BSON bytes, on a user's preferences subdocument.
+const MAX_PREFERENCES_BYTES: usize = 16 * 1024;
+
+/// Upper bound, in serialized BSON bytes, on a user document when `MAX_DOC_BYTES` is unset.
+///
+/// Kept well below MongoDB's 16 MiB document limit.
+const DEFAULT_MAX_DOC_BYTES: usize = 1024 * 1024;
+
+/// Content type of `GET /users/export`: one JSON user per line.
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Version reported for a user in the minimal `POST /user` response; new users start at 1.
+const INITIAL_USER_VERSION: u32 = 1;
+
+/// Query parameters accepted by `GET /user/{id}`.
+#[derive(Debug, Deserialize)]
+pub struct GetUserQuery {
+    /// When `true`, the user is returned with HAL-style `_links`.
+    pub hal: Option<bool>,
+}
+
+/// Query parameters accepted by `GET /users/find`.
+#[derive(Debug, Deserialize)]
+pub struct FindUserQuery {
+    /// The exact name of the user.
+    pub name: String,
+    /// The exact location of the user.
+    pub location: String,
+}
+
+/// Query parameters accepted by `GET /users/by-location` and `DELETE /users`.
+#[derive(Debug, Deserialize)]
+pub struct LocationQuery {
+    /// The exact location to match.
+    pub location: String,
+}
+
+/// Body returned by `DELETE /users`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteByLocationResponse {
+    /// The number of users that were deleted.
+    pub deleted: u64,
+}
+
+/// Query parameters accepted by `GET /users/page`.
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    /// Only count and return users in this exact location.
+    pub location: Option<String>,
+    /// The 1-based page to return. Defaults to 1.
+    pub page: Option<u64>,
+    /// The number of users per page. Defaults to 20 and is capped at `MAX_PAGE_SIZE`.
+    pub per_page: Option<i64>,
+}
+
+/// Query parameters accepted by `GET /users/cursor`.
+#[derive(Debug, Deserialize)]
+pub struct CursorQuery {
+    /// The `last_id` of the previous page; omit it to start from the beginning.
+    pub after: Option<String>,
+    /// The number of users per page. Defaults to 20 and is capped at `MAX_PAGE_SIZE`.
+    pub limit: Option<i64>,
+}
+
+/// Body returned by `GET /users/cursor`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CursorPageResponse {
+    /// The users on this page, in ascending `_id` order.
+    pub users: Vec<UserResponse>,
+    /// The ID to pass as `after` to fetch the next page. Absent when the page is empty.
+    pub last_id: Option<String>,
+}
+
+/// Query parameters accepted by `GET /users/count`.
+#[derive(Debug, Deserialize)]
+pub struct CountQuery {
+    /// When set, only users in this exact location are counted.
+    pub location: Option<String>,
+}
+
+/// Body returned by `GET /users/count`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CountResponse {
+    pub count: u64,
+}
+
+/// Query parameters accepted by `GET /user/{id}/colleagues`.
+#[derive(Debug, Deserialize)]
+pub struct ColleaguesQuery {
+    /// Maximum number of colleagues to return. Defaults to 10.
+    pub limit: Option<i64>,
+}
+
+/// Body returned by `GET /user/{id}/colleagues`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColleaguesResponse {
+    pub user: UserResponse,
+    pub colleagues: Vec<UserResponse>,
+}
+
+/// Body returned by `GET /users/page`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserPageResponse {
+    /// The users on the requested page.
+    pub users: Vec<UserResponse>,
+    /// The number of users matching the query across all pages.
+    pub total: u64,
+}
+
+/// Body returned by `GET /users/scroll`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScrollResponse {
+    /// The ID to pass back to continue the scroll.
+    pub scroll_id: String,
+    /// The users in this batch, in ascending `_id` order.
+    pub users: Vec<UserResponse>,
+    /// Whether the scroll has reached the end of the collection.
+    pub done: bool,
+}
+
+/// Query parameters accepted by `GET /users/search`.
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Text to look for anywhere in user names, ignoring case.
+    pub name: String,
+}
+
+/// Body accepted by `POST /users/bulk-delete`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkDeleteRequest {
+    /// The IDs of the users to delete, as hex strings.
+    pub ids: Vec<String>,
+}
+
+/// Outcome of one item of a bulk request.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkItemResult {
+    /// Position of the item in the request.
+    pub index: usize,
+    /// HTTP status the item would have received as a request of its own.
+    pub status: u16,
+    /// ID of the user the item refers to, as a hex string, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Why the item failed, as the error body it would have received on its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+}
+
+/// Body returned by `POST /users/bulk`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkCreateResponse {
+    /// IDs of the created users, as hex strings, in the order the users were sent.
+    pub ids: Vec<String>,
+    /// Outcome of every user sent, in request order.
+    pub results: Vec<BulkItemResult>,
+}
+
+/// Per-ID outcome of `POST /users/bulk-delete`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BulkDeleteReport {
+    /// IDs of the users that were deleted.
+    pub deleted: Vec<String>,
+    /// Well-formed IDs that did not match any user.
+    pub not_found: Vec<String>,
+    /// Inputs that are not valid ObjectIds.
+    pub invalid: Vec<String>,
+    /// Outcome of every ID sent, in request order.
+    pub results: Vec<BulkItemResult>,
+}
+
+/// Query parameters accepted by `GET /users/active`.
+#[derive(Debug, Deserialize)]
+pub struct ActiveQuery {
+    /// How many minutes back a user may have been seen to count as active. Defaults to 15.
+    pub within_minutes: Option<i64>,
+}
+
+/// Body accepted by `POST /user/{id}/claim`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClaimRequest {
+    /// An identifier of the worker taking the claim.
+    pub worker: String,
+    /// How long the claim lasts, in seconds. Defaults to 60.
+    pub ttl_seconds: Option<u64>,
+}
+
+/// Body accepted by `POST /user/{id}/release`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReleaseRequest {
+    /// The worker that holds the claim.
+    pub worker: String,
+}
+
+/// Query parameters accepted by `GET /users/scroll`.
+#[derive(Debug, Deserialize)]
+pub struct ScrollQuery {
+    /// The scroll to resume; omitted to start a new one.
+    pub scroll_id: Option<String>,
+    /// The batch size, capped at `MAX_SCROLL_SIZE`. Defaults to 100.
+    pub size: Option<i64>,
+}
+
+/// Query parameters accepted by `GET /users`.
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    /// Comma-separated fields to return instead of the default projection.
+    pub fields: Option<String>,
+    /// Field to sort by: `name`, `location` or `title`, prefixed with `-` for descending order.
+    pub sort: Option<String>,
+}
+
+/// Query parameters accepted by `GET /users/sample`.
+#[derive(Debug, Deserialize)]
+pub struct SampleQuery {
+    /// The requested sample size, capped at `MAX_SAMPLE_SIZE`.
+    pub n: Option<i64>,
+}
+
+/// Returns the fields left out of list responses unless requested through `?fields=`.
+///
+/// Read once from the comma-separated `HEAVY_FIELDS` variable, defaulting to `preferences`.
+fn heavy_fields() -> &'static [String] {
+    static HEAVY_FIELDS: OnceLock<Vec<String>> = OnceLock::new();
+    HEAVY_FIELDS.get_or_init(|| match env::var("HEAVY_FIELDS") {
+        Ok(fields) => parse_fields(&fields),
+        Err(_) => vec![String::from("preferences")],
+    })
+}
+
+/// Returns the largest user document, in serialized BSON bytes, accepted on create or update.
+///
+/// Read once from `MAX_DOC_BYTES`, defaulting to `DEFAULT_MAX_DOC_BYTES`.
+fn max_doc_bytes() -> usize {
+    static MAX_DOC_BYTES: OnceLock<usize> = OnceLock::new();
+    *MAX_DOC_BYTES.get_or_init(|| {
+        env::var("MAX_DOC_BYTES")
+            .ok()
+            .and_then(|bytes| bytes.parse().ok())
+            .unwrap_or(DEFAULT_MAX_DOC_BYTES)
+    })
+}
+
+/// Returns the error response for a user about to be written whose BSON encoding exceeds
+/// `max_doc_bytes`.
+fn reject_oversized(user: &User) -> Option<HttpResponse> {
+    match to_vec(user) {
+        Ok(bytes) if bytes.len() > max_doc_bytes() => Some(error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "user document exceeds the size limit",
+        )),
+        Ok(_) => None,
+        Err(err) => Some(error_response(StatusCode::BAD_REQUEST, err.to_string())),
+    }
+}
+
+/// Fills in a blank `location` from the configured `UserDefaults`, if one is configured.
+fn apply_default_location(req: &HttpRequest, user: &mut User) {
+    if !user.location.trim().is_empty() {
+        return;
+    }
+    let default = req
+        .app_data::<Data<UserDefaults>>()
+        .and_then(|defaults| defaults.location.clone());
+    if let Some(location) = default {
+        user.location = location;
+    }
+}
+
+/// Rejects a payload that failed validation with `422 Unprocessable Entity` and its field errors.
+fn reject_invalid(validation: Result<(), ValidationErrors>) -> Option<HttpResponse> {
+    validation.err().map(validation_response)
+}
+
+/// Rejects a `referred_by` that does not name an existing user with `422 Unprocessable Entity`.
+async fn reject_unknown_referrer(
+    db: &MongoRepo,
+    referred_by: Option<ObjectId>,
+) -> Option<HttpResponse> {
+    let referrer = referred_by?;
+    match db.get_user(&referrer.to_hex(), None).await {
+        Ok(Some(_)) => None,
+        Ok(None) => {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "referred_by",
+                ValidationError::new("unknown_referrer")
+                    .with_message(Cow::from("must name an existing user")),
+            );
+            Some(validation_response(errors))
+        }
+        Err(err) => Some(repo_error_response(err)),
+    }
+}
+
+/// Turns the error response an item of a bulk request would have received on its own into its
+/// per-item result.
+async fn failed_item(index: usize, resp: HttpResponse) -> BulkItemResult {
+    let status = resp.status().as_u16();
+    let error = to_bytes(resp.into_body())
+        .await
+        .ok()
+        .and_then(|body| serde_json::from_slice(&body).ok());
+    BulkItemResult {
+        index,
+        status,
+        id: None,
+        error,
+    }
+}
+
+/// Responds to a bulk request with `200 OK` when every item succeeded and `207 Multi-Status`
+/// when any failed; `body` carries the per-item results either way.
+fn bulk_response(results: &[BulkItemResult], body: impl Serialize) -> HttpResponse {
+    let status = if results.iter().all(|result| result.status < 400) {
+        StatusCode::OK
+    } else {
+        StatusCode::MULTI_STATUS
+    };
+    HttpResponse::build(status).json(body)
+}
+
+/// Splits a comma-separated field list, dropping blank entries.
+fn parse_fields(fields: &str) -> Vec<String> {
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Returns the first entry in `fields` that cannot be used as a projection path.
+fn invalid_field(fields: &[String]) -> Option<&str> {
+    fields
+        .iter()
+        .map(String::as_str)
+        .find(|field| field.starts_with('$') || field.split('.').any(str::is_empty))
+}
+
+/// Returns the origin generated links are prefixed with.
+///
+/// Uses the configured `LinkConfig::public_base_url`, falling back to the scheme and host the
+/// request was received on (honouring `Forwarded`/`X-Forwarded-*` headers).
+fn base_url(req: &HttpRequest) -> String {
+    let configured = req
+        .app_data::<Data<LinkConfig>>()
+        .and_then(|config| config.public_base_url.clone());
+    match configured {
+        Some(base_url) => base_url,
+        None => {
+            let info = req.connection_info();
+            format!("{}://{}", info.scheme(), info.host())
+        }
+    }
+}
+
+/// Serializes `user` keeping only `fields` (plus `_id`), or dropping the heavy fields when `None`.
+///
+/// Mirrors the projection `GET /users` asks MongoDB for when it can push it down to the query.
+fn project_user(user: User, fields: Option<&[String]>) -> serde_json::Value {
+    let mut value = serde_json::to_value(UserResponse::from(user)).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        match fields {
+            Some(fields) => object.retain(|key, _| key == "_id" || fields.contains(key)),
+            None => object.retain(|key, _| !heavy_fields().contains(key)),
+        }
+    }
+    value
+}
+
+/// Converts users into their API representation.
+fn user_responses(users: Vec<User>) -> Vec<UserResponse> {
+    users.into_iter().map(UserResponse::from).collect()
+}
+
+/// Renders the ObjectIds of a projected user document as hex strings, like `UserResponse` does.
+fn with_hex_ids(mut document: Document) -> Document {
+    for key in ["_id", "referred_by"] {
+        if let Some(Bson::ObjectId(id)) = document.get(key) {
+            let hex = id.to_hex();
+            document.insert(key, hex);
+        }
+    }
+    document
+}
+
+/// Builds the canonical URL of a single user resource.
+fn user_url(req: &HttpRequest, id: &ObjectId) -> String {
+    format!("{}/user/{}", base_url(req), id.to_hex())
+}
+
+/// Returns `true` when `id` is a well-formed ObjectId hex string.
+pub fn is_valid_object_id(id: &str) -> bool {
+    ObjectId::parse_str(id).is_ok()
+}
+
+/// Returns the read concern requested through `X-Read-Concern`, or `None` when the header is absent.
+fn read_concern(req: &HttpRequest) -> Result<Option<ReadConcern>, RepoError> {
+    match req.headers().get("X-Read-Concern") {
+        Some(value) => {
+            let level = value.to_str().unwrap_or_default();
+            parse_read_concern(level).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Returns `true` when the request carries `Prefer: return=minimal`.
+fn prefers_minimal(req: &HttpRequest) -> bool {
+    req.headers()
+        .get_all("Prefer")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|preference| preference.trim().eq_ignore_ascii_case("return=minimal"))
+}
+
+/// Serializes `user` with a HAL-style `_links` object pointing at itself and its collection.
+fn with_hal_links(req: &HttpRequest, user: &User) -> serde_json::Value {
+    let mut body = serde_json::to_value(UserResponse::from(user.clone())).unwrap_or_default();
+    let collection_url = format!("{}/users", base_url(req));
+    let mut links = serde_json::json!({ "collection": { "href": collection_url } });
+    if let Some(id) = &user.id {
+        links["self"] = serde_json::json!({ "href": user_url(req, id) });
+    }
+    body["_links"] = links;
+    body
+}
 
 #[post("/user")]
-pub async fn create_user(db: Data<MongoRepo>, new_user: Json<User>) -> HttpResponse {
+pub async fn create_user(
+    req: HttpRequest,
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    new_user: Json<User>,
+) -> HttpResponse {
+    let mut new_user = new_user.into_inner();
+    apply_default_location(&req, &mut new_user);
     let data = User {
         id: None,
-        name: new_user.name.to_owned(),
-        location: new_user.location.to_owned(),
-        title: new_user.title.to_owned(),
+        name: new_user.name,
+        location: new_user.location,
+        title: new_user.title,
+        email: new_user.email,
+        referred_by: new_user.referred_by,
+        ..Default::default()
     };
+    if let Some(resp) = reject_oversized(&data) {
+        return resp;
+    }
+    if let Some(resp) = reject_invalid(data.validate()) {
+        return resp;
+    }
+    if let Some(resp) = reject_unknown_referrer(&db, data.referred_by).await {
+        return resp;
+    }
 
     let user_detail = db.create_user(data).await;
 
     match user_detail {
-        Ok(user) => HttpResponse::Ok().json(user),
-        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        Ok(user) => {
+            events.publish(UserEvent::Created { user: user.clone() });
+            match user.id {
+                Some(id) if prefers_minimal(&req) => HttpResponse::Created()
+                    .insert_header((LOCATION, user_url(&req, &id)))
+                    .insert_header(("Preference-Applied", "return=minimal"))
+                    .json(serde_json::json!({
+                        "_id": id.to_hex(),
+                        "version": INITIAL_USER_VERSION,
+                    })),
+                Some(id) => {
+                    let url = user_url(&req, &id);
+                    HttpResponse::Created()
+                        .insert_header((LOCATION, url.clone()))
+                        .insert_header((CONTENT_LOCATION, url))
+                        .json(UserResponse::from(user))
+                }
+                None => HttpResponse::Created().json(UserResponse::from(user)),
+            }
+        }
+        Err(err) => repo_error_response(err),
     }
 }
 
 #[get("/user/{id}")]
-pub async fn get_user(db: Data<MongoRepo>, path: Path<String>) -> HttpResponse {
+pub async fn get_user(
+    req: HttpRequest,
+    db: Data<MongoRepo>,
+    path: Path<String>,
+    query: Query<GetUserQuery>,
+) -> HttpResponse {
     let id = path.into_inner();
-    if id.is_empty() {
-        return HttpResponse::BadRequest().body("invalid ID");
-    }
-    let user_detail = db.get_user(&id).await;
+    let read_concern = match read_concern(&req) {
+        Ok(read_concern) => read_concern,
+        Err(err) => return repo_error_response(err),
+    };
+    let user_detail = db.get_user(&id, read_concern).await;
 
     match user_detail {
-        Ok(user) => HttpResponse::Ok().json(user),
-        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        Ok(Some(user)) if query.hal == Some(true) => HttpResponse::Ok()
+            .insert_header(("X-Content-Hash", user.content_hash()))
+            .json(with_hal_links(&req, &user)),
+        Ok(Some(user)) => HttpResponse::Ok()
+            .insert_header(("X-Content-Hash", user.content_hash()))
+            .json(UserResponse::from(user)),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "No user found with specified ID"),
+        Err(err) => repo_error_response(err),
     }
 }
 
 #[put("/user/{id}")]
 pub async fn update_user(
+    req: HttpRequest,
     db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
     path: Path<String>,
     new_user: Json<User>,
 ) -> HttpResponse {
     let id = path.into_inner();
-    if id.is_empty() {
-        return HttpResponse::BadRequest().body("invalid ID");
+    let obj_id = match parse_object_id(&id) {
+        Ok(obj_id) => obj_id,
+        Err(err) => return repo_error_response(err),
     };
+    let new_user = new_user.into_inner();
     let data = User {
-        id: Some(ObjectId::parse_str(&id).unwrap()),
-        name: new_user.name.to_owned(),
-        location: new_user.location.to_owned(),
-        title: new_user.title.to_owned(),
+        id: Some(obj_id),
+        name: new_user.name,
+        location: new_user.location,
+        title: new_user.title,
+        email: new_user.email,
+        ..Default::default()
     };
+    if let Some(resp) = reject_oversized(&data) {
+        return resp;
+    }
+    if let Some(resp) = reject_invalid(data.validate()) {
+        return resp;
+    }
 
     let update_result = db.update_user(&id, data).await;
 
     match update_result {
-        Ok(update) => {
-            if update.matched_count == 1 {
-                let updated_user_info = db.get_user(&id).await;
+        Ok(_) => {
+            let updated_user_info = db.get_user(&id, None).await;
 
-                return match updated_user_info {
-                    Ok(user) => HttpResponse::Ok().json(user),
-                    Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
-                };
-            } else {
-                return HttpResponse::NotFound().body("No user found with specified ID");
+            match updated_user_info {
+                Ok(Some(user)) => {
+                    events.publish(UserEvent::Updated { user: user.clone() });
+                    let url = user.id.map(|id| user_url(&req, &id)).unwrap_or_default();
+                    HttpResponse::Ok()
+                        .insert_header((CONTENT_LOCATION, url))
+                        .json(UserResponse::from(user))
+                }
+                Ok(None) => {
+                    error_response(StatusCode::NOT_FOUND, "No user found with specified ID")
+                }
+                Err(err) => repo_error_response(err),
             }
         }
-        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[patch("/user/{id}")]
+pub async fn patch_user(
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    path: Path<String>,
+    fields: Json<UserPatch>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    if let Some(resp) = reject_invalid(fields.validate()) {
+        return resp;
+    }
+    let result = db.patch_user(&id, fields.into_inner()).await;
+
+    match result {
+        Ok(user) => {
+            events.publish(UserEvent::Updated { user: user.clone() });
+            HttpResponse::Ok().json(UserResponse::from(user))
+        }
+        Err(err) => repo_error_response(err),
     }
 }
 
 #[delete("/user/{id}")]
-pub async fn delete_user(db: Data<MongoRepo>, path: Path<String>) -> HttpResponse {
+pub async fn delete_user(
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    path: Path<String>,
+) -> HttpResponse {
     let id = path.into_inner();
-    if id.is_empty() {
-        return HttpResponse::BadRequest().body("invalid ID");
-    };
     let result = db.delete_user(&id).await;
 
     match result {
-        Ok(res) => {
-            if res.deleted_count == 1 {
-                return HttpResponse::Ok().json("User successfully deleted!");
-            } else {
-                return HttpResponse::NotFound().json("User with specified ID not found!");
-            }
+        Ok(_) => {
+            events.publish(UserEvent::Deleted { id });
+            HttpResponse::Ok().json("User successfully deleted!")
+        }
+        Err(RepoError::NotFound) => {
+            error_response(StatusCode::NOT_FOUND, "User with specified ID not found!")
         }
-        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        Err(err) => repo_error_response(err),
     }
 }
 
 #[get("/users")]
-pub async fn get_all_users(db: Data<MongoRepo>) -> HttpResponse {
-    let users = db.get_all_users().await;
+pub async fn get_all_users(
+    req: HttpRequest,
+    db: Data<MongoRepo>,
+    query: Query<ListQuery>,
+) -> HttpResponse {
+    let read_concern = match read_concern(&req) {
+        Ok(read_concern) => read_concern,
+        Err(err) => return repo_error_response(err),
+    };
+    let fields = query.fields.as_deref().map(parse_fields);
+    if let Some(field) = fields.as_deref().and_then(invalid_field) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("invalid field name: {}", field),
+        );
+    }
+    // Taken before the listing, so a write racing with it makes the header older, never newer.
+    let last_modified = match db.last_modified().await {
+        Ok(last_modified) => last_modified,
+        Err(err) => return repo_error_response(err),
+    };
+    let ok = || {
+        let mut resp = HttpResponse::Ok();
+        if let Some(last_modified) = last_modified {
+            resp.insert_header(LastModified(last_modified.to_system_time().into()));
+        }
+        resp
+    };
+    if let Some(sort) = &query.sort {
+        let (sort_field, ascending) = match sort.strip_prefix('-') {
+            Some(sort_field) => (sort_field, false),
+            None => (sort.as_str(), true),
+        };
+        let users = db
+            .get_all_users_sorted(sort_field, ascending, read_concern)
+            .await;
+
+        return match users {
+            Ok(users) => ok().json(
+                users
+                    .into_iter()
+                    .map(|user| project_user(user, fields.as_deref()))
+                    .collect::<Vec<_>>(),
+            ),
+            Err(err) => repo_error_response(err),
+        };
+    }
+    let users = match &fields {
+        Some(fields) => {
+            let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+            db.list_users_projected(&fields, read_concern).await
+        }
+        None if heavy_fields().is_empty() => {
+            let users = db.get_all_users(read_concern).await;
+
+            return match users {
+                Ok(users) => ok().json(user_responses(users)),
+                Err(err) => repo_error_response(err),
+            };
+        }
+        None => {
+            let projection = heavy_fields()
+                .iter()
+                .map(|field| (field.to_owned(), Bson::Int32(0)))
+                .collect::<Document>();
+            db.find_users_with_projection(projection, read_concern)
+                .await
+        }
+    };
 
     match users {
-        Ok(users) => HttpResponse::Ok().json(users),
-        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        Ok(users) => ok().json(users.into_iter().map(with_hex_ids).collect::<Vec<_>>()),
+        Err(err) => repo_error_response(err),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use actix_web::App;
-    use actix_web::http::StatusCode;
-    use actix_web::test;
-    use crate::repository::mongodb_repo::MongoRepo;
+#[get("/users/sample")]
+pub async fn sample_users(db: Data<MongoRepo>, query: Query<SampleQuery>) -> HttpResponse {
+    let n = query.n.unwrap_or(MAX_SAMPLE_SIZE);
+    if n < 1 {
+        return error_response(StatusCode::BAD_REQUEST, "sample size must be positive");
+    }
+    let users = db.sample_users(n.min(MAX_SAMPLE_SIZE)).await;
 
-    #[tokio::test]
-    async fn test_create_user() {
-        // Arrange
-        let mut app = test::init_service(App::new().data(MongoRepo::init().await)).await;
-        let new_user = User {
-            id: None,
-            name: String::from("Test User"),
-            location: String::from("Test Location"),
-            title: String::from("Test Title"),
-        };
-        let req = test::TestRequest::post()
-            .uri("/user")
-            .set_json(&new_user)
-            .to_request();
+    match users {
+        Ok(users) => HttpResponse::Ok().json(user_responses(users)),
+        Err(err) => repo_error_response(err),
+    }
+}
 
-        // Act
-        let resp = test::call_service(&mut app, req).await;
+#[get("/user/{id}/preferences")]
+pub async fn get_preferences(db: Data<MongoRepo>, path: Path<String>) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let preferences = db.get_preferences(&id).await;
 
-        // Assert
-        assert_eq!(resp.status(), StatusCode::OK);
+    match preferences {
+        Ok(Some(preferences)) => HttpResponse::Ok().json(preferences),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "No user found with specified ID"),
+        Err(err) => repo_error_response(err),
     }
+}
 
-    #[tokio::test]
-    async fn test_get_user() {
-        // Arrange
-        let mut app = test::init_service(App::new().data(MongoRepo::init().await)).await;
-        let id = "some_id"; // Provide an existing user ID
-        let req = test::TestRequest::get().uri(&format!("/user/{}", id)).to_request();
+#[put("/user/{id}/preferences")]
+pub async fn update_preferences(
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    path: Path<String>,
+    preferences: Json<Document>,
+) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let preferences = preferences.into_inner();
+    match to_vec(&preferences) {
+        Ok(bytes) if bytes.len() > MAX_PREFERENCES_BYTES => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "preferences exceed the size limit",
+            );
+        }
+        Ok(_) => {}
+        Err(err) => return error_response(StatusCode::BAD_REQUEST, err.to_string()),
+    }
+    let update_result = db.set_preferences(&id, preferences.clone()).await;
 
-        // Act
-        let resp = test::call_service(&mut app, req).await;
+    match update_result {
+        Ok(update) => {
+            if update.matched_count == 1 {
+                if let Ok(Some(user)) = db.get_user(&id.to_hex(), None).await {
+                    events.publish(UserEvent::Updated { user });
+                }
+                HttpResponse::Ok().json(preferences)
+            } else {
+                error_response(StatusCode::NOT_FOUND, "No user found with specified ID")
+            }
+        }
+        Err(err) => repo_error_response(err),
+    }
+}
 
-        // Assert
-        assert_eq!(resp.status(), StatusCode::OK);
+#[get("/users/find")]
+pub async fn find_user(db: Data<MongoRepo>, query: Query<FindUserQuery>) -> HttpResponse {
+    let user_detail = db
+        .get_by_name_and_location(&query.name, &query.location)
+        .await;
+
+    match user_detail {
+        Ok(Some(user)) => HttpResponse::Ok().json(UserResponse::from(user)),
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            "No user found with specified name and location",
+        ),
+        Err(err) => repo_error_response(err),
     }
+}
 
-    #[tokio::test]
-    async fn test_update_user() {
-        // Arrange
-        let mut app = test::init_service(App::new().data(MongoRepo::init().await)).await;
-        let id = "some_id"; // Provide an existing user ID
-        let updated_user = User {
-            id: None, // Provide a new ID or the same ID
-            name: String::from("Updated Name"),
-            location: String::from("Updated Location"),
-            title: String::from("Updated Title"),
-        };
-        let req = test::TestRequest::put()
-            .uri(&format!("/user/{}", id))
-            .set_json(&updated_user)
-            .to_request();
+#[get("/users/search")]
+pub async fn search_users(db: Data<MongoRepo>, query: Query<SearchQuery>) -> HttpResponse {
+    let users = db.search_users_by_name(&query.name).await;
 
-        // Act
-        let resp = test::call_service(&mut app, req).await;
+    match users {
+        Ok(users) => HttpResponse::Ok().json(user_responses(users)),
+        Err(err) => repo_error_response(err),
+    }
+}
 
-        // Assert
-        assert_eq!(resp.status(), StatusCode::OK);
+#[get("/users/by-location")]
+pub async fn get_users_by_location(
+    db: Data<MongoRepo>,
+    query: Query<LocationQuery>,
+) -> HttpResponse {
+    let users = db.get_users_by_location(&query.location).await;
+
+    match users {
+        Ok(users) => HttpResponse::Ok().json(user_responses(users)),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[delete("/users")]
+pub async fn delete_users_by_location(
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    query: Query<LocationQuery>,
+) -> HttpResponse {
+    let result = db.delete_users_by_location(&query.location).await;
+
+    match result {
+        Ok(deleted) => {
+            for id in &deleted {
+                events.publish(UserEvent::Deleted { id: id.to_hex() });
+            }
+            HttpResponse::Ok().json(DeleteByLocationResponse {
+                deleted: deleted.len() as u64,
+            })
+        }
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/page")]
+pub async fn get_users_page(db: Data<MongoRepo>, query: Query<PageQuery>) -> HttpResponse {
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let page = query.page.unwrap_or(1).max(1);
+    // The skip is sent to MongoDB as an i64, so pages past that are rejected rather than wrapped.
+    let skip = (page - 1)
+        .checked_mul(per_page.unsigned_abs())
+        .filter(|skip| i64::try_from(*skip).is_ok());
+    let Some(skip) = skip else {
+        return error_response(StatusCode::BAD_REQUEST, "page is out of range");
+    };
+    let filter = match &query.location {
+        Some(location) => doc! {"location": location},
+        None => Document::new(),
+    };
+    let options = FindOptions::builder()
+        .sort(doc! {"_id": 1})
+        .skip(skip)
+        .limit(per_page)
+        .build();
+    let result = db.find_with_count(filter, options).await;
+
+    match result {
+        Ok((users, total)) => HttpResponse::Ok().json(UserPageResponse {
+            users: user_responses(users),
+            total,
+        }),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/cursor")]
+pub async fn get_users_after(db: Data<MongoRepo>, query: Query<CursorQuery>) -> HttpResponse {
+    let after_id = match query.after.as_deref().map(parse_object_id).transpose() {
+        Ok(after_id) => after_id,
+        Err(err) => return repo_error_response(err),
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let users = db.get_users_after(after_id, limit).await;
+
+    match users {
+        Ok(users) => {
+            let last_id = users.last().and_then(|user| user.id).map(|id| id.to_hex());
+            HttpResponse::Ok().json(CursorPageResponse {
+                users: user_responses(users),
+                last_id,
+            })
+        }
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/count")]
+pub async fn count_users(db: Data<MongoRepo>, query: Query<CountQuery>) -> HttpResponse {
+    let count = db.count_users(query.location.as_deref()).await;
+
+    match count {
+        Ok(count) => HttpResponse::Ok().json(CountResponse { count }),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/filter-options")]
+pub async fn get_filter_options(db: Data<MongoRepo>) -> HttpResponse {
+    let options = db.filter_options().await;
+
+    match options {
+        Ok(options) => HttpResponse::Ok().json(options),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[post("/users/bulk")]
+pub async fn create_many_users(
+    req: HttpRequest,
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    new_users: Json<Vec<User>>,
+) -> HttpResponse {
+    let new_users = new_users.into_inner();
+    if new_users.is_empty() {
+        return repo_error_response(RepoError::EmptyBatch);
+    }
+    let mut results = Vec::new();
+    let mut accepted = Vec::new();
+    for (index, mut new_user) in new_users.into_iter().enumerate() {
+        apply_default_location(&req, &mut new_user);
+        let data = User {
+            id: None,
+            name: new_user.name,
+            location: new_user.location,
+            title: new_user.title,
+            email: new_user.email,
+            referred_by: new_user.referred_by,
+            ..Default::default()
+        };
+        let rejection = match reject_oversized(&data) {
+            Some(resp) => Some(resp),
+            None => reject_invalid(data.validate()),
+        };
+        let rejection = match rejection {
+            Some(resp) => Some(resp),
+            None => reject_unknown_referrer(&db, data.referred_by).await,
+        };
+        if let Some(resp) = rejection {
+            results.push(failed_item(index, resp).await);
+            continue;
+        }
+        accepted.push((index, data));
+    }
+    let outcomes = if accepted.is_empty() {
+        Vec::new()
+    } else {
+        let users = accepted.iter().map(|(_, user)| user.clone()).collect();
+        match db.create_many_users(users).await {
+            Ok(outcomes) => outcomes,
+            Err(err) => return repo_error_response(err),
+        }
+    };
+
+    let mut ids = Vec::new();
+    for ((index, mut user), outcome) in accepted.into_iter().zip(outcomes) {
+        match outcome {
+            Ok(id) => {
+                ids.push(id.to_hex());
+                results.push(BulkItemResult {
+                    index,
+                    status: StatusCode::CREATED.as_u16(),
+                    id: Some(id.to_hex()),
+                    error: None,
+                });
+                user.id = Some(id);
+                events.publish(UserEvent::Created { user });
+            }
+            Err(err) => results.push(failed_item(index, repo_error_response(err)).await),
+        }
+    }
+    results.sort_by_key(|result| result.index);
+    let body = BulkCreateResponse { ids, results };
+    bulk_response(&body.results, &body)
+}
+
+#[post("/users/bulk-delete")]
+pub async fn bulk_delete_users(
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    request: Json<BulkDeleteRequest>,
+) -> HttpResponse {
+    let mut report = BulkDeleteReport::default();
+    let mut ids: Vec<(usize, ObjectId)> = Vec::new();
+    for (index, raw) in request.ids.iter().enumerate() {
+        match ObjectId::parse_str(raw) {
+            Ok(id) => ids.push((index, id)),
+            Err(_) => {
+                report.invalid.push(raw.to_owned());
+                let resp = repo_error_response(RepoError::InvalidObjectId(raw.to_owned()));
+                report.results.push(BulkItemResult {
+                    id: Some(raw.to_owned()),
+                    ..failed_item(index, resp).await
+                });
+            }
+        }
+    }
+    let object_ids = ids.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+    let result = db.delete_users_by_ids(&object_ids).await;
+
+    match result {
+        Ok(deleted) => {
+            for (index, id) in ids {
+                if deleted.contains(&id) {
+                    events.publish(UserEvent::Deleted { id: id.to_hex() });
+                    report.deleted.push(id.to_hex());
+                    report.results.push(BulkItemResult {
+                        index,
+                        status: StatusCode::OK.as_u16(),
+                        id: Some(id.to_hex()),
+                        error: None,
+                    });
+                } else {
+                    report.not_found.push(id.to_hex());
+                    let resp = repo_error_response(RepoError::NotFound);
+                    report.results.push(BulkItemResult {
+                        id: Some(id.to_hex()),
+                        ..failed_item(index, resp).await
+                    });
+                }
+            }
+            report.results.sort_by_key(|result| result.index);
+            bulk_response(&report.results, &report)
+        }
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/stats/location-title")]
+pub async fn get_title_distribution(db: Data<MongoRepo>) -> HttpResponse {
+    let distribution = db.title_distribution_by_location().await;
+
+    match distribution {
+        Ok(distribution) => HttpResponse::Ok().json(distribution),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[post("/user/{id}/touch")]
+pub async fn touch_user(db: Data<MongoRepo>, path: Path<String>) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let result = db.touch_user(&id).await;
+
+    match result {
+        Ok(update) => {
+            if update.matched_count == 1 {
+                HttpResponse::NoContent().finish()
+            } else {
+                error_response(StatusCode::NOT_FOUND, "No user found with specified ID")
+            }
+        }
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/active")]
+pub async fn get_active_users(db: Data<MongoRepo>, query: Query<ActiveQuery>) -> HttpResponse {
+    let within_minutes = query.within_minutes.unwrap_or(15);
+    if within_minutes < 0 {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "within_minutes must not be negative",
+        );
+    }
+    let since = DateTime::from_millis(
+        DateTime::now()
+            .timestamp_millis()
+            .saturating_sub(within_minutes.saturating_mul(60 * 1000)),
+    );
+    let users = db.active_since(since).await;
+
+    match users {
+        Ok(users) => HttpResponse::Ok().json(user_responses(users)),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[post("/user/{id}/claim")]
+pub async fn claim_user(
+    db: Data<MongoRepo>,
+    path: Path<String>,
+    request: Json<ClaimRequest>,
+) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let ttl = Duration::from_secs(request.ttl_seconds.unwrap_or(60));
+    let result = db.claim_user(&id, &request.worker, ttl).await;
+
+    match result {
+        Ok(Some(user)) => HttpResponse::Ok().json(UserResponse::from(user)),
+        Ok(None) => error_response(StatusCode::CONFLICT, "User not found or already claimed"),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[post("/user/{id}/release")]
+pub async fn release_user(
+    db: Data<MongoRepo>,
+    path: Path<String>,
+    request: Json<ReleaseRequest>,
+) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let result = db.release_user(&id, &request.worker).await;
+
+    match result {
+        Ok(update) => {
+            if update.matched_count == 1 {
+                HttpResponse::NoContent().finish()
+            } else {
+                error_response(StatusCode::CONFLICT, "Claim not held by this worker")
+            }
+        }
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/scroll")]
+pub async fn scroll_users(db: Data<MongoRepo>, query: Query<ScrollQuery>) -> HttpResponse {
+    let scroll_id = match query.scroll_id.as_deref().map(ObjectId::parse_str) {
+        Some(Ok(scroll_id)) => Some(scroll_id),
+        Some(Err(_)) => return error_response(StatusCode::BAD_REQUEST, "invalid scroll ID"),
+        None => None,
+    };
+    let size = query.size.unwrap_or(100);
+    if size < 1 {
+        return error_response(StatusCode::BAD_REQUEST, "size must be positive");
+    }
+    let page = db.scroll_users(scroll_id, size.min(MAX_SCROLL_SIZE)).await;
+
+    match page {
+        Ok(Some(page)) => HttpResponse::Ok().json(ScrollResponse {
+            scroll_id: page.scroll_id,
+            users: user_responses(page.users),
+            done: page.done,
+        }),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Scroll not found or expired"),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+/// Response body of `GET /user/{id}/content-hash`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentHashResponse {
+    pub content_hash: String,
+}
+
+#[get("/user/{id}/content-hash")]
+pub async fn get_content_hash(db: Data<MongoRepo>, path: Path<String>) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let result = db.content_hash(&id).await;
+
+    match result {
+        Ok(Some(content_hash)) => HttpResponse::Ok().json(ContentHashResponse { content_hash }),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "No user found with specified ID"),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+/// Response body of `GET /validate-id/{id}`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidateIdResponse {
+    pub valid: bool,
+}
+
+#[get("/validate-id/{id}")]
+pub async fn validate_id(path: Path<String>) -> HttpResponse {
+    HttpResponse::Ok().json(ValidateIdResponse {
+        valid: is_valid_object_id(&path),
+    })
+}
+
+#[get("/user/{id}/referrals")]
+pub async fn get_referrals(db: Data<MongoRepo>, path: Path<String>) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let referrals = db.find_referrals(&id).await;
+
+    match referrals {
+        Ok(referrals) => HttpResponse::Ok().json(user_responses(referrals)),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/user/{id}/colleagues")]
+pub async fn get_colleagues(
+    db: Data<MongoRepo>,
+    path: Path<String>,
+    query: Query<ColleaguesQuery>,
+) -> HttpResponse {
+    let id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid ID"),
+    };
+    let limit = query.limit.unwrap_or(10);
+    if !(1..=MAX_COLLEAGUES).contains(&limit) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {}", MAX_COLLEAGUES),
+        );
+    }
+    let result = db.get_with_colleagues(&id, limit).await;
+
+    match result {
+        Ok(Some((user, colleagues))) => HttpResponse::Ok().json(ColleaguesResponse {
+            user: UserResponse::from(user),
+            colleagues: user_responses(colleagues),
+        }),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "No user found with specified ID"),
+        Err(err) => repo_error_response(err),
+    }
+}
+
+#[get("/users/export")]
+pub async fn export_users(db: Data<MongoRepo>) -> HttpResponse {
+    let users = match db.stream_users().await {
+        Ok(users) => users,
+        Err(err) => return repo_error_response(err),
+    };
+    let lines = users.map(|user| {
+        let mut line = serde_json::to_vec(&UserResponse::from(user?))?;
+        line.push(b'\n');
+        Ok::<_, Box<dyn Error>>(Bytes::from(line))
+    });
+
+    HttpResponse::Ok()
+        .content_type(NDJSON_CONTENT_TYPE)
+        .streaming(lines)
+}
+
+#[put("/users/by-email")]
+pub async fn upsert_user_by_email(
+    req: HttpRequest,
+    db: Data<MongoRepo>,
+    events: Data<dyn EventSink>,
+    new_user: Json<User>,
+) -> HttpResponse {
+    if let Some(resp) = reject_oversized(&new_user) {
+        return resp;
+    }
+    if let Some(resp) = reject_invalid(new_user.validate()) {
+        return resp;
+    }
+    let created = match db.upsert_user_by_email(&new_user).await {
+        Ok(result) => result.upserted_id.is_some(),
+        Err(err) => return repo_error_response(err),
+    };
+    let user = match db.get_user_by_email(&new_user.email).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return error_response(StatusCode::NOT_FOUND, "No user found with specified email")
+        }
+        Err(err) => return repo_error_response(err),
+    };
+
+    let url = user.id.map(|id| user_url(&req, &id)).unwrap_or_default();
+    if created {
+        events.publish(UserEvent::Created { user: user.clone() });
+        HttpResponse::Created()
+            .insert_header((LOCATION, url.clone()))
+            .insert_header((CONTENT_LOCATION, url))
+            .json(UserResponse::from(user))
+    } else {
+        events.publish(UserEvent::Updated { user: user.clone() });
+        HttpResponse::Ok()
+            .insert_header((CONTENT_LOCATION, url))
+            .json(UserResponse::from(user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::event_sink::NoopSink;
+    use crate::repository::mongodb_repo::{page_options, MongoRepo};
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use actix_web::App;
+    use std::sync::{Arc, Mutex};
+
+    fn noop_sink() -> Data<dyn EventSink> {
+        Data::from(Arc::new(NoopSink) as Arc<dyn EventSink>)
+    }
+
+    /// Sink that records every published event for inspection.
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<UserEvent>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn publish(&self, event: UserEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_user() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .app_data(noop_sink())
+                .service(create_user),
+        )
+        .await;
+        let new_user = User {
+            id: None,
+            name: String::from("Test User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(&new_user)
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let user: UserResponse = test::read_body_json(resp).await;
+
+        // Assert
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(ObjectId::parse_str(&user.id).is_ok());
+        assert_eq!(user.name, new_user.name);
+        assert!(user.member_number.is_some());
+        assert!(user.created_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_user() {
+        // Arrange
+        let app =
+            test::init_service(App::new().app_data(Data::new(MongoRepo::init().await.unwrap())))
+                .await;
+        let id = "some_id"; // Provide an existing user ID
+        let req = test::TestRequest::get()
+            .uri(&format!("/user/{}", id))
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
+        // Arrange
+        let app =
+            test::init_service(App::new().app_data(Data::new(MongoRepo::init().await.unwrap())))
+                .await;
+        let id = "some_id"; // Provide an existing user ID
+        let updated_user = User {
+            id: None, // Provide a new ID or the same ID
+            name: String::from("Updated Name"),
+            location: String::from("Updated Location"),
+            title: String::from("Updated Title"),
+            ..Default::default()
+        };
+        let req = test::TestRequest::put()
+            .uri(&format!("/user/{}", id))
+            .set_json(&updated_user)
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_patch_return_hex_ids() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let inserted = repo
+            .create_user(User {
+                name: String::from("Hex User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        let id = inserted.id.unwrap().to_hex();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(repo))
+                .app_data(noop_sink())
+                .service(update_user)
+                .service(patch_user),
+        )
+        .await;
+        let put_req = test::TestRequest::put()
+            .uri(&format!("/user/{}", id))
+            .set_json(User {
+                name: String::from("Hex User"),
+                location: String::from("Updated Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .to_request();
+        let patch_req = test::TestRequest::patch()
+            .uri(&format!("/user/{}", id))
+            .set_json(UserPatch {
+                title: Some(String::from("Patched Title")),
+                ..Default::default()
+            })
+            .to_request();
+
+        // Act
+        let put_body: serde_json::Value =
+            test::read_body_json(test::call_service(&app, put_req).await).await;
+        let patch_body: serde_json::Value =
+            test::read_body_json(test::call_service(&app, patch_req).await).await;
+
+        // Assert
+        assert_eq!(put_body["_id"], id);
+        assert_eq!(patch_body["_id"], id);
+    }
+
+    #[tokio::test]
+    async fn test_create_user_sets_location_headers() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .app_data(noop_sink())
+                .service(create_user)
+                .service(get_user),
+        )
+        .await;
+        let new_user = User {
+            id: None,
+            name: String::from("Located User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(&new_user)
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let location = resp
+            .headers()
+            .get(LOCATION)
+            .expect("Location header missing")
+            .clone();
+        let content_location = resp
+            .headers()
+            .get(CONTENT_LOCATION)
+            .expect("Content-Location header missing")
+            .clone();
+        assert_eq!(location, content_location);
+        let created: UserResponse = test::read_body_json(resp).await;
+        assert_eq!(
+            location.to_str().unwrap(),
+            format!("http://localhost:8080/user/{}", created.id)
+        );
+        let path = location
+            .to_str()
+            .unwrap()
+            .trim_start_matches("http://localhost:8080");
+        let get_req = test::TestRequest::get().uri(path).to_request();
+        let fetched: UserResponse = test::call_and_read_body_json(&app, get_req).await;
+        assert_eq!(fetched.id, created.id);
+        assert_eq!(fetched.name, "Located User");
+    }
+
+    #[tokio::test]
+    async fn test_update_and_get_preferences() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let inserted = repo
+            .create_user(User {
+                id: None,
+                name: String::from("Preferences User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        let id = inserted.id.unwrap().to_hex();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(repo))
+                .app_data(noop_sink())
+                .service(get_preferences)
+                .service(update_preferences),
+        )
+        .await;
+        let preferences = mongodb::bson::doc! {"theme": "dark", "layout": {"sidebar": true}};
+        let oversized = mongodb::bson::doc! {"blob": "x".repeat(MAX_PREFERENCES_BYTES + 1)};
+
+        // Act
+        let put_req = test::TestRequest::put()
+            .uri(&format!("/user/{}/preferences", id))
+            .set_json(&preferences)
+            .to_request();
+        let put_resp = test::call_service(&app, put_req).await;
+        let get_req = test::TestRequest::get()
+            .uri(&format!("/user/{}/preferences", id))
+            .to_request();
+        let stored: Document = test::call_and_read_body_json(&app, get_req).await;
+        let oversized_req = test::TestRequest::put()
+            .uri(&format!("/user/{}/preferences", id))
+            .set_json(&oversized)
+            .to_request();
+        let oversized_resp = test::call_service(&app, oversized_req).await;
+
+        // Assert
+        assert_eq!(put_resp.status(), StatusCode::OK);
+        assert_eq!(stored, preferences);
+        assert_eq!(oversized_resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_users_with_trailing_slash() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .wrap(actix_web::middleware::NormalizePath::trim())
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .service(get_all_users),
+        )
+        .await;
+
+        // Act
+        let req = test::TestRequest::get().uri("/users").to_request();
+        let resp = test::call_service(&app, req).await;
+        let slash_req = test::TestRequest::get().uri("/users/").to_request();
+        let slash_resp = test::call_service(&app, slash_req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(slash_resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_with_hal_links() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let inserted = repo
+            .create_user(User {
+                id: None,
+                name: String::from("HAL User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        let id = inserted.id.unwrap().to_hex();
+        let app = test::init_service(App::new().app_data(Data::new(repo)).service(get_user)).await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/user/{}?hal=true", id))
+            .to_request();
+
+        // Act
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert
+        assert_eq!(
+            body["_links"]["self"]["href"],
+            format!("http://localhost:8080/user/{}", id)
+        );
+        assert_eq!(
+            body["_links"]["collection"]["href"],
+            "http://localhost:8080/users"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_delete_users_reports_each_id() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let inserted = repo
+            .create_user(User {
+                id: None,
+                name: String::from("Bulk Delete User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        let existing = inserted.id.unwrap().to_hex();
+        let missing = ObjectId::new().to_hex();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(repo))
+                .app_data(noop_sink())
+                .service(bulk_delete_users),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/users/bulk-delete")
+            .set_json(BulkDeleteRequest {
+                ids: vec![existing.clone(), missing.clone(), String::from("not-an-id")],
+            })
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let report: BulkDeleteReport = test::read_body_json(resp).await;
+
+        // Assert
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        assert_eq!(report.deleted, vec![existing]);
+        assert_eq!(report.not_found, vec![missing]);
+        assert_eq!(report.invalid, vec![String::from("not-an-id")]);
+        let statuses = report
+            .results
+            .iter()
+            .map(|result| (result.index, result.status))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec![(0, 200), (1, 404), (2, 400)]);
+    }
+
+    #[tokio::test]
+    async fn test_create_many_users_reports_partial_failure() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .app_data(noop_sink())
+                .service(create_many_users),
+        )
+        .await;
+        let email = format!("{}@example.com", ObjectId::new().to_hex());
+        let users = (0..3)
+            .map(|i| User {
+                name: format!("Bulk User {}", i),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                email: if i == 2 { String::new() } else { email.clone() },
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let req = test::TestRequest::post()
+            .uri("/users/bulk")
+            .set_json(users)
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let body: BulkCreateResponse = test::read_body_json(resp).await;
+
+        // Assert
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        let statuses = body
+            .results
+            .iter()
+            .map(|result| (result.index, result.status))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec![(0, 201), (1, 409), (2, 201)]);
+        assert_eq!(body.ids.len(), 2);
+        let error = body.results[1].error.as_ref().expect("error missing");
+        assert_eq!(error.code, 409);
+        assert_eq!(error.error, RepoError::DuplicateEmail.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_create_user_with_minimal_return() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .app_data(noop_sink())
+                .service(create_user),
+        )
+        .await;
+        let new_user = User {
+            id: None,
+            name: String::from("Minimal User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post()
+            .uri("/user")
+            .insert_header(("Prefer", "return=minimal"))
+            .set_json(&new_user)
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let location = resp
+            .headers()
+            .get(LOCATION)
+            .expect("Location header missing")
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let id = body["_id"].as_str().expect("_id missing from body");
+        assert_eq!(body["version"], 1);
+        assert_eq!(body.as_object().unwrap().len(), 2);
+        assert_eq!(location, format!("http://localhost:8080/user/{}", id));
+    }
+
+    #[tokio::test]
+    async fn test_create_user_publishes_event() {
+        // Arrange
+        let sink = Arc::new(RecordingSink::default());
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .app_data(Data::from(sink.clone() as Arc<dyn EventSink>))
+                .service(create_user),
+        )
+        .await;
+        let new_user = User {
+            id: None,
+            name: String::from("Evented User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(&new_user)
+            .to_request();
+
+        // Act
+        let created: UserResponse = test::call_and_read_body_json(&app, req).await;
+
+        // Assert
+        let events = sink.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            UserEvent::Created { user } => {
+                assert_eq!(user.id.map(|id| id.to_hex()), Some(created.id.clone()));
+                assert_eq!(user.name, "Evented User");
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_all_users_excludes_heavy_fields_by_default() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = ObjectId::new();
+        repo.create_user(User {
+            id: Some(id),
+            name: String::from("Heavy User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            preferences: Some(mongodb::bson::doc! {"theme": "dark"}),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to seed user");
+        let app =
+            test::init_service(App::new().app_data(Data::new(repo)).service(get_all_users)).await;
+        let seeded_user = |users: Vec<serde_json::Value>| {
+            users
+                .into_iter()
+                .find(|user| user["_id"] == id.to_hex())
+                .expect("Seeded user missing from list")
+        };
+
+        // Act
+        let default_req = test::TestRequest::get().uri("/users").to_request();
+        let default_users: Vec<serde_json::Value> =
+            test::call_and_read_body_json(&app, default_req).await;
+        let fields_req = test::TestRequest::get()
+            .uri("/users?fields=name,preferences")
+            .to_request();
+        let fields_users: Vec<serde_json::Value> =
+            test::call_and_read_body_json(&app, fields_req).await;
+
+        // Assert
+        let default_user = seeded_user(default_users);
+        assert!(default_user.get("preferences").is_none());
+        assert_eq!(default_user["name"], "Heavy User");
+        let fields_user = seeded_user(fields_users);
+        assert_eq!(fields_user["preferences"]["theme"], "dark");
+        assert!(fields_user.get("location").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_validate_id() {
+        // Arrange
+        let app = test::init_service(App::new().service(validate_id)).await;
+        let valid_req = test::TestRequest::get()
+            .uri(&format!("/validate-id/{}", ObjectId::new().to_hex()))
+            .to_request();
+        let invalid_req = test::TestRequest::get()
+            .uri("/validate-id/not-an-id")
+            .to_request();
+
+        // Act
+        let valid: ValidateIdResponse = test::call_and_read_body_json(&app, valid_req).await;
+        let invalid: ValidateIdResponse = test::call_and_read_body_json(&app, invalid_req).await;
+
+        // Assert
+        assert!(valid.valid);
+        assert!(!invalid.valid);
+    }
+
+    #[tokio::test]
+    async fn test_create_user_rejects_oversized_document() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .app_data(noop_sink())
+                .service(create_user),
+        )
+        .await;
+        let oversized = User {
+            name: String::from("Oversized User"),
+            location: "x".repeat(DEFAULT_MAX_DOC_BYTES),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        let ignored_preferences = User {
+            name: String::from("Oversized User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            preferences: Some(mongodb::bson::doc! {"blob": "x".repeat(DEFAULT_MAX_DOC_BYTES)}),
+            ..Default::default()
+        };
+
+        // Act
+        let req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(&oversized)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let ignored_req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(&ignored_preferences)
+            .to_request();
+        let ignored_resp = test::call_service(&app, ignored_req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(ignored_resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_create_user_uses_public_base_url() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .app_data(noop_sink())
+                .app_data(Data::new(LinkConfig {
+                    public_base_url: Some(String::from("https://api.example.com")),
+                }))
+                .service(create_user),
+        )
+        .await;
+        let new_user = User {
+            name: String::from("Linked User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(&new_user)
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let location = resp
+            .headers()
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .expect("Location header missing");
+        assert!(location.starts_with("https://api.example.com/user/"));
+    }
+
+    #[tokio::test]
+    async fn test_create_user_fills_default_location() {
+        // Arrange
+        let db = Data::new(MongoRepo::init().await.unwrap());
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .app_data(noop_sink())
+                .app_data(Data::new(UserDefaults {
+                    location: Some(String::from("Remote")),
+                }))
+                .service(create_user),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(serde_json::json!({
+                "name": "Defaulted User",
+                "title": "Test Title",
+            }))
+            .to_request();
+
+        // Act
+        let created: UserResponse = test::call_and_read_body_json(&app, req).await;
+        let stored = db
+            .get_user(&created.id, None)
+            .await
+            .unwrap()
+            .expect("Created user should exist");
+
+        // Assert
+        assert_eq!(created.location, "Remote");
+        assert_eq!(stored.location, "Remote");
+    }
+
+    #[tokio::test]
+    async fn test_get_referrals() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let referrer = ObjectId::new();
+        let seed = |name: &str, id: ObjectId, referred_by: Option<ObjectId>| User {
+            id: Some(id),
+            name: String::from(name),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            referred_by,
+            ..Default::default()
+        };
+        let referred = [ObjectId::new(), ObjectId::new()];
+        repo.create_user(seed("Referrer", referrer, None))
+            .await
+            .expect("Failed to seed user");
+        for id in referred {
+            repo.create_user(seed("Referred User", id, Some(referrer)))
+                .await
+                .expect("Failed to seed user");
+        }
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(repo))
+                .app_data(noop_sink())
+                .service(get_referrals)
+                .service(create_user),
+        )
+        .await;
+        let unknown_referral = User {
+            id: None,
+            ..seed("Unknown Referrer", ObjectId::new(), Some(ObjectId::new()))
+        };
+
+        // Act
+        let req = test::TestRequest::get()
+            .uri(&format!("/user/{}/referrals", referrer))
+            .to_request();
+        let referrals: Vec<UserResponse> = test::call_and_read_body_json(&app, req).await;
+        let unknown_req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(&unknown_referral)
+            .to_request();
+        let unknown_resp = test::call_service(&app, unknown_req).await;
+
+        // Assert
+        let mut ids: Vec<ObjectId> = referrals
+            .iter()
+            .filter_map(|user| ObjectId::parse_str(&user.id).ok())
+            .collect();
+        ids.sort();
+        let mut expected = referred.to_vec();
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert_eq!(unknown_resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_user_returns_not_found() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .service(get_user),
+        )
+        .await;
+
+        // Act
+        let req = test::TestRequest::get()
+            .uri(&format!("/user/{}", ObjectId::new()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_id_returns_bad_request() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::init().await.unwrap()))
+                .service(get_user),
+        )
+        .await;
+
+        // Act
+        let req = test::TestRequest::get().uri("/user/abc").to_request();
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let body: serde_json::Value = test::read_body_json(resp).await;
+
+        // Assert
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "invalid object id", "code": 400 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_concern_header_overrides_find_options() {
+        // Arrange
+        let majority = test::TestRequest::get()
+            .uri("/users")
+            .insert_header(("X-Read-Concern", "majority"))
+            .to_http_request();
+        let invalid = test::TestRequest::get()
+            .uri("/users")
+            .insert_header(("X-Read-Concern", "eventual"))
+            .to_http_request();
+        let absent = test::TestRequest::get().uri("/users").to_http_request();
+
+        // Act
+        let options = page_options(0, 0, read_concern(&majority).unwrap());
+        let rejected = read_concern(&invalid).unwrap_err();
+        let default = read_concern(&absent).unwrap();
+
+        // Assert
+        assert_eq!(options.read_concern, Some(ReadConcern::majority()));
+        assert_eq!(
+            repo_error_response(rejected).status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert!(default.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_responses_render_object_ids_as_hex() {
+        // Arrange
+        let id = ObjectId::new();
+        let referrer = ObjectId::new();
+        let user = User {
+            id: Some(id),
+            name: String::from("Hex User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            referred_by: Some(referrer),
+            ..Default::default()
+        };
+        let document = mongodb::bson::to_document(&user).unwrap();
+
+        // Act
+        let response = serde_json::to_value(UserResponse::from(user)).unwrap();
+        let projected = serde_json::to_value(with_hex_ids(document)).unwrap();
+
+        // Assert
+        assert_eq!(response["_id"], id.to_hex());
+        assert_eq!(response["referred_by"], referrer.to_hex());
+        assert_eq!(projected["_id"], id.to_hex());
+        assert_eq!(projected["referred_by"], referrer.to_hex());
+    }
+
+    #[tokio::test]
+    async fn test_export_users_streams_ndjson() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let inserted = repo
+            .create_user(User {
+                name: String::from("Exported User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        let id = inserted.id.unwrap().to_hex();
+        let app =
+            test::init_service(App::new().app_data(Data::new(repo)).service(export_users)).await;
+        let req = test::TestRequest::get().uri("/users/export").to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+        let content_type = resp.headers().get("content-type").cloned();
+        let body = test::read_body(resp).await;
+
+        // Assert
+        assert_eq!(content_type.unwrap(), NDJSON_CONTENT_TYPE);
+        let users = body
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<UserResponse>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert!(users.iter().any(|user| user.id == id));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_user_payload_returns_field_errors() {
+        // Arrange
+        // The client only connects on first use, so no database is needed for rejected payloads.
+        let client = mongodb::Client::with_uri_str("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::from_client(client, "rustDB")))
+                .app_data(noop_sink())
+                .service(create_user)
+                .service(patch_user),
+        )
+        .await;
+        let create_req = test::TestRequest::post()
+            .uri("/user")
+            .set_json(User {
+                name: String::new(),
+                location: String::from("Test Location"),
+                title: "x".repeat(10_000),
+                ..Default::default()
+            })
+            .to_request();
+        let patch_req = test::TestRequest::patch()
+            .uri(&format!("/user/{}", ObjectId::new()))
+            .set_json(UserPatch {
+                location: Some(String::from(" ")),
+                ..Default::default()
+            })
+            .to_request();
+
+        // Act
+        let create_resp = test::call_service(&app, create_req).await;
+        let create_status = create_resp.status();
+        let create_errors: serde_json::Value = test::read_body_json(create_resp).await;
+        let patch_resp = test::call_service(&app, patch_req).await;
+        let patch_status = patch_resp.status();
+        let patch_errors: serde_json::Value = test::read_body_json(patch_resp).await;
+
+        // Assert
+        assert_eq!(create_status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            create_errors,
+            serde_json::json!({
+                "error": "validation failed",
+                "code": 422,
+                "fields": {
+                    "name": ["must be between 1 and 120 characters"],
+                    "title": ["must be between 1 and 120 characters"],
+                },
+            })
+        );
+        assert_eq!(patch_status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            patch_errors["fields"],
+            serde_json::json!({ "location": ["must not be empty"] })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_all_users_rejects_invalid_fields() {
+        // Arrange
+        // The field list is checked before any query, so no database is needed.
+        let client = mongodb::Client::with_uri_str("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::from_client(client, "rustDB")))
+                .service(get_all_users),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/users?fields=name,$where")
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let body: serde_json::Value = test::read_body_json(resp).await;
+
+        // Assert
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid field name: $where");
+    }
+
+    #[tokio::test]
+    async fn test_get_users_page_rejects_out_of_range_page() {
+        // Arrange
+        // The page is checked before any query, so no database is needed.
+        let client = mongodb::Client::with_uri_str("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(MongoRepo::from_client(client, "rustDB")))
+                .service(get_users_page),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/users/page?page={}", u64::MAX))
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let body: serde_json::Value = test::read_body_json(resp).await;
+
+        // Assert
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "page is out of range");
     }
 }
\ No newline at end of file
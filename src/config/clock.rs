@@ -0,0 +1,17 @@
+use std::time::SystemTime;
+
+/// A source of the current time, shared through app data so tests can control it.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A `Clock` backed by the system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
\ No newline at end of file
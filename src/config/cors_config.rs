@@ -0,0 +1,60 @@
+use std::env;
+
+/// Methods allowed cross-origin when `CORS_ALLOWED_METHODS` is not set.
+const DEFAULT_METHODS: &str = "GET,POST,PUT,PATCH,DELETE";
+
+/// Request headers allowed cross-origin when `CORS_ALLOWED_HEADERS` is not set.
+const DEFAULT_HEADERS: &str = "content-type";
+
+/// Settings for answering cross-origin requests from browsers.
+///
+/// The default denies every origin: no CORS headers are sent, so browsers block
+/// cross-origin calls until origins are listed or permissive mode is turned on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorsConfig {
+    /// Allows any origin, method and header. Meant for local development only.
+    pub permissive: bool,
+    /// Origins, such as `https://app.example.com`, that may call the API.
+    pub allowed_origins: Vec<String>,
+    /// Upper-case methods allowed in cross-origin requests.
+    pub allowed_methods: Vec<String>,
+    /// Lower-case request headers allowed in cross-origin requests.
+    pub allowed_headers: Vec<String>,
+}
+
+impl CorsConfig {
+    /// Loads the CORS settings from the environment.
+    ///
+    /// # Returns
+    ///
+    /// A `CorsConfig` built from the comma-separated `CORS_ALLOWED_ORIGINS`,
+    /// `CORS_ALLOWED_METHODS` and `CORS_ALLOWED_HEADERS`, with `permissive` set when
+    /// `CORS_PERMISSIVE` is `true`.
+    pub fn from_env() -> Self {
+        let list = |name: &str, default: &str| {
+            split_list(&env::var(name).unwrap_or_else(|_| default.to_owned()))
+        };
+        CorsConfig {
+            permissive: env::var("CORS_PERMISSIVE").is_ok_and(|value| value == "true"),
+            allowed_origins: list("CORS_ALLOWED_ORIGINS", ""),
+            allowed_methods: list("CORS_ALLOWED_METHODS", DEFAULT_METHODS)
+                .into_iter()
+                .map(|method| method.to_ascii_uppercase())
+                .collect(),
+            allowed_headers: list("CORS_ALLOWED_HEADERS", DEFAULT_HEADERS)
+                .into_iter()
+                .map(|header| header.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+}
+
+/// Splits a comma-separated list, dropping blank entries.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
\ No newline at end of file
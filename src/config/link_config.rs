@@ -0,0 +1,26 @@
+use std::env;
+
+/// Settings for the links the API puts in headers and response bodies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkConfig {
+    /// Absolute URL, without a trailing slash, that generated links are prefixed with.
+    ///
+    /// When `None`, links are derived from the scheme and host of the incoming request.
+    pub public_base_url: Option<String>,
+}
+
+impl LinkConfig {
+    /// Loads the link settings from the environment.
+    ///
+    /// # Returns
+    ///
+    /// A `LinkConfig` whose `public_base_url` is taken from `PUBLIC_BASE_URL` when it is set.
+    pub fn from_env() -> Self {
+        LinkConfig {
+            public_base_url: env::var("PUBLIC_BASE_URL")
+                .ok()
+                .map(|url| url.trim_end_matches('/').to_owned())
+                .filter(|url| !url.is_empty()),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,59 @@
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A daily window, in UTC, during which writes are rejected.
+///
+/// The window may cross midnight, e.g. `23:30-00:30`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    /// Start of the window, in seconds since midnight UTC.
+    start: u64,
+    /// End of the window, in seconds since midnight UTC.
+    end: u64,
+}
+
+impl MaintenanceWindow {
+    /// Returns how many seconds remain until the window ends, or `None` if `now` is outside it.
+    pub fn seconds_remaining(&self, now: SystemTime) -> Option<u64> {
+        let since_epoch = now.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let time_of_day = since_epoch % SECONDS_PER_DAY;
+        let elapsed = (time_of_day + SECONDS_PER_DAY - self.start) % SECONDS_PER_DAY;
+        let length = (self.end + SECONDS_PER_DAY - self.start) % SECONDS_PER_DAY;
+        (elapsed < length).then_some(length - elapsed)
+    }
+}
+
+/// Parses `HH:MM` into seconds since midnight.
+fn parse_time_of_day(time: &str) -> Option<u64> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let hours: u64 = hours.parse().ok()?;
+    let minutes: u64 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 3600 + minutes * 60)
+}
+
+impl FromStr for MaintenanceWindow {
+    type Err = String;
+
+    /// Parses a window such as `02:00-02:30 UTC`. The `UTC` suffix is optional.
+    fn from_str(window: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid maintenance window '{}', expected HH:MM-HH:MM UTC",
+                window
+            )
+        };
+        let window_utc = window.trim();
+        let range = window_utc.strip_suffix("UTC").unwrap_or(window_utc);
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let start = parse_time_of_day(start).ok_or_else(invalid)?;
+        let end = parse_time_of_day(end).ok_or_else(invalid)?;
+        if start == end {
+            return Err(invalid());
+        }
+        Ok(MaintenanceWindow { start, end })
+    }
+}
\ No newline at end of file
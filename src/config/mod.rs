@@ -0,0 +1,7 @@
+pub mod clock;
+pub mod cors_config;
+pub mod link_config;
+pub mod maintenance_window;
+pub mod runtime_config;
+pub mod signing_config;
+pub mod user_defaults;
\ No newline at end of file
@@ -0,0 +1,49 @@
+use std::env;
+
+use crate::config::maintenance_window::MaintenanceWindow;
+
+/// Settings that can be changed while the server is running.
+///
+/// Connection settings such as `MONGOURI` are deliberately not part of this struct; they are
+/// only read once at startup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// When `true`, every request that would modify data is rejected.
+    pub read_only: bool,
+    /// Daily window during which requests that would modify data are rejected.
+    pub maintenance_window: Option<MaintenanceWindow>,
+}
+
+impl RuntimeConfig {
+    /// Loads the runtime settings from the environment.
+    ///
+    /// # Returns
+    ///
+    /// A `RuntimeConfig` where `read_only` is enabled when `READ_ONLY=true` and the maintenance
+    /// window is parsed from `MAINTENANCE_WINDOW` (e.g. `02:00-02:30 UTC`). A window that cannot
+    /// be parsed is ignored, so a typo never takes the API offline.
+    pub fn from_env() -> Self {
+        RuntimeConfig {
+            read_only: env::var("READ_ONLY").is_ok_and(|value| value == "true"),
+            maintenance_window: env::var("MAINTENANCE_WINDOW")
+                .ok()
+                .and_then(|window| window.parse().ok()),
+        }
+    }
+}
+/// Where `POST /admin/config/reload` reads the runtime settings from, shared through app data
+/// so tests can supply settings without touching the environment.
+pub trait RuntimeConfigSource: Send + Sync {
+    /// Returns the current runtime settings.
+    fn load(&self) -> RuntimeConfig;
+}
+
+/// A `RuntimeConfigSource` backed by the process environment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvConfigSource;
+
+impl RuntimeConfigSource for EnvConfigSource {
+    fn load(&self) -> RuntimeConfig {
+        RuntimeConfig::from_env()
+    }
+}
\ No newline at end of file
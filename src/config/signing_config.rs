@@ -0,0 +1,23 @@
+use std::env;
+
+/// Settings for verifying signed server-to-server requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SigningConfig {
+    /// Shared secret requests must be signed with. Verification is skipped when `None`.
+    pub hmac_secret: Option<String>,
+}
+
+impl SigningConfig {
+    /// Loads the signing settings from the environment.
+    ///
+    /// # Returns
+    ///
+    /// A `SigningConfig` whose `hmac_secret` is taken from `HMAC_SECRET` when it is set.
+    pub fn from_env() -> Self {
+        SigningConfig {
+            hmac_secret: env::var("HMAC_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,26 @@
+use std::env;
+
+/// Values filled in for fields that clients leave out when creating users.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserDefaults {
+    /// Location stored for new users that have no location.
+    ///
+    /// When `None`, a location is required and creating a user without one is rejected.
+    pub location: Option<String>,
+}
+
+impl UserDefaults {
+    /// Loads the user defaults from the environment.
+    ///
+    /// # Returns
+    ///
+    /// A `UserDefaults` whose `location` is taken from `DEFAULT_LOCATION` when it is set and not blank.
+    pub fn from_env() -> Self {
+        UserDefaults {
+            location: env::var("DEFAULT_LOCATION")
+                .ok()
+                .map(|location| location.trim().to_owned())
+                .filter(|location| !location.is_empty()),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,64 @@
+use actix_web::rt;
+use serde::Serialize;
+
+use crate::models::user_model::User;
+
+/// A change to a user, published after the database write succeeded.
+///
+/// Touching, claiming and releasing a user publish nothing: they only change bookkeeping
+/// fields (`last_seen` and the claim) that the content hash ignores, and heartbeat traffic
+/// would flood consumers with events that carry no change to the user.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum UserEvent {
+    /// A user was created.
+    #[serde(rename = "UserCreated")]
+    Created { user: User },
+    /// A user was updated; `user` is the stored state after the update.
+    #[serde(rename = "UserUpdated")]
+    Updated { user: User },
+    /// A user was deleted.
+    #[serde(rename = "UserDeleted")]
+    Deleted { id: String },
+}
+
+/// Destination for user change events.
+///
+/// Implementations must not block: `publish` is called from request handlers right after the
+/// database write, and delivery failures must never fail the request that caused the event.
+pub trait EventSink: Send + Sync {
+    /// Publishes `event` to the sink.
+    fn publish(&self, event: UserEvent);
+}
+
+/// Sink that drops every event. Used when no event destination is configured.
+#[derive(Debug, Default)]
+pub struct NoopSink;
+
+impl EventSink for NoopSink {
+    fn publish(&self, _event: UserEvent) {}
+}
+
+/// Sink that POSTs every event as JSON to a webhook URL.
+#[derive(Debug)]
+pub struct WebhookSink {
+    url: String,
+}
+
+impl WebhookSink {
+    /// Creates a sink delivering events to `url`.
+    pub fn new(url: String) -> Self {
+        WebhookSink { url }
+    }
+}
+
+impl EventSink for WebhookSink {
+    fn publish(&self, event: UserEvent) {
+        let url = self.url.clone();
+        rt::spawn(async move {
+            if let Err(err) = awc::Client::default().post(&url).send_json(&event).await {
+                eprintln!("Failed to deliver user event to {}: {}", url, err);
+            }
+        });
+    }
+}
\ No newline at end of file
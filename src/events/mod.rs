@@ -0,0 +1 @@
+pub mod event_sink;
\ No newline at end of file
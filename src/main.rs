@@ -1,23 +1,129 @@
 mod api;
+mod config;
+mod events;
+mod middleware;
 mod models;
 mod repository;
 
-use actix_web::{web::Data, App, HttpServer};
-use api::user_api::{create_user, delete_user, get_all_users, get_user, update_user};
+use std::{env, io, sync::Arc};
+
+use actix_web::{
+    middleware::{from_fn, Condition, NormalizePath},
+    web::{self, Data, JsonConfig, PathConfig, PayloadConfig, QueryConfig},
+    App, HttpServer,
+};
+use api::admin_api::{
+    backfill_timestamps, dump_users, get_metrics, health, livez, readyz, reconnect, reload_config,
+    restore_users,
+};
+use api::api_error::{extractor_error, route_not_found};
+use api::user_api::{
+    bulk_delete_users, claim_user, count_users, create_many_users, create_user, delete_user,
+    delete_users_by_location, export_users, find_user, get_active_users, get_all_users,
+    get_colleagues, get_content_hash, get_filter_options, get_preferences, get_referrals,
+    get_title_distribution, get_user, get_users_after, get_users_by_location, get_users_page,
+    patch_user, release_user, sample_users, scroll_users, search_users, touch_user,
+    update_preferences, update_user, upsert_user_by_email, validate_id,
+};
+use arc_swap::ArcSwap;
+use config::{
+    clock::{Clock, SystemClock},
+    cors_config::CorsConfig,
+    link_config::LinkConfig,
+    runtime_config::{EnvConfigSource, RuntimeConfig, RuntimeConfigSource},
+    signing_config::SigningConfig,
+    user_defaults::UserDefaults,
+};
+use events::event_sink::{EventSink, NoopSink, WebhookSink};
+use middleware::{
+    cors::cors, pretty_json::pretty_json, read_only::read_only, signature::verify_signature,
+};
 use repository::mongodb_repo::MongoRepo;
 
+/// Upper bound on raw request bodies, such as the BSON dumps sent to `/admin/restore`.
+const MAX_RAW_PAYLOAD_BYTES: usize = 64 * 1024 * 1024;
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let db = MongoRepo::init().await;
+    let db = MongoRepo::init()
+        .await
+        .map_err(|err| io::Error::other(err.to_string()))?;
     let db_data = Data::new(db);
+    let event_sink: Arc<dyn EventSink> = match env::var("WEBHOOK_URL") {
+        Ok(url) => Arc::new(WebhookSink::new(url)),
+        Err(_) => Arc::new(NoopSink),
+    };
+    let events = Data::from(event_sink);
+    let runtime_config = Data::new(ArcSwap::from_pointee(RuntimeConfig::from_env()));
+    let config_source: Arc<dyn RuntimeConfigSource> = Arc::new(EnvConfigSource);
+    let config_source = Data::from(config_source);
+    let cors_config = CorsConfig::from_env();
+    let link_config = Data::new(LinkConfig::from_env());
+    let signing_config = Data::new(SigningConfig::from_env());
+    let user_defaults = Data::new(UserDefaults::from_env());
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    let clock = Data::from(clock);
+    let normalize_path = env::var("NORMALIZE_PATH").is_ok_and(|value| value == "true");
     HttpServer::new(move || {
         App::new()
+            .wrap(Condition::new(normalize_path, NormalizePath::trim()))
+            .wrap(from_fn(pretty_json))
+            .wrap(from_fn(read_only))
+            .wrap(from_fn(verify_signature))
+            .wrap(cors(&cors_config))
             .app_data(db_data.clone())
+            .app_data(runtime_config.clone())
+            .app_data(config_source.clone())
+            .app_data(link_config.clone())
+            .app_data(signing_config.clone())
+            .app_data(user_defaults.clone())
+            .app_data(clock.clone())
+            .app_data(events.clone())
+            .app_data(PayloadConfig::new(MAX_RAW_PAYLOAD_BYTES))
+            .app_data(JsonConfig::default().error_handler(extractor_error))
+            .app_data(QueryConfig::default().error_handler(extractor_error))
+            .app_data(PathConfig::default().error_handler(extractor_error))
+            .default_service(web::to(route_not_found))
             .service(create_user)
             .service(get_user)
             .service(update_user)
             .service(delete_user)
             .service(get_all_users)
+            .service(sample_users)
+            .service(get_preferences)
+            .service(update_preferences)
+            .service(find_user)
+            .service(reload_config)
+            .service(get_filter_options)
+            .service(bulk_delete_users)
+            .service(dump_users)
+            .service(restore_users)
+            .service(get_title_distribution)
+            .service(touch_user)
+            .service(get_active_users)
+            .service(reconnect)
+            .service(get_metrics)
+            .service(backfill_timestamps)
+            .service(health)
+            .service(livez)
+            .service(readyz)
+            .service(claim_user)
+            .service(release_user)
+            .service(scroll_users)
+            .service(get_content_hash)
+            .service(validate_id)
+            .service(search_users)
+            .service(get_referrals)
+            .service(get_users_by_location)
+            .service(count_users)
+            .service(get_colleagues)
+            .service(patch_user)
+            .service(create_many_users)
+            .service(delete_users_by_location)
+            .service(get_users_page)
+            .service(get_users_after)
+            .service(export_users)
+            .service(upsert_user_by_email)
     })
     .bind(("127.0.0.1", 8080))?
     .run()
@@ -0,0 +1,144 @@
+use actix_cors::Cors;
+use actix_web::http::header::{CONTENT_LOCATION, LAST_MODIFIED, LOCATION, RETRY_AFTER};
+
+use crate::config::cors_config::CorsConfig;
+
+/// Seconds browsers may cache a successful preflight.
+const PREFLIGHT_MAX_AGE_SECS: usize = 600;
+
+/// Builds the `Cors` middleware for `config`.
+///
+/// With the default config no origin is allowed: preflights are rejected and other responses
+/// carry no CORS headers, so browsers block cross-origin calls. Permissive mode allows any
+/// origin, method and header. Invalid origins, methods or headers, such as a `*` origin, make
+/// the app fail to start rather than being silently ignored.
+pub fn cors(config: &CorsConfig) -> Cors {
+    if config.permissive {
+        return Cors::permissive();
+    }
+    let cors = Cors::default()
+        .allowed_methods(config.allowed_methods.iter().map(String::as_str))
+        .allowed_headers(config.allowed_headers.iter().map(String::as_str))
+        .expose_headers([LOCATION, CONTENT_LOCATION, LAST_MODIFIED, RETRY_AFTER])
+        .max_age(PREFLIGHT_MAX_AGE_SECS);
+    config
+        .allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{
+        header::{
+            ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+            ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+        },
+        Method, StatusCode,
+    };
+    use actix_web::{test, web, App, HttpResponse};
+
+    fn app_config() -> CorsConfig {
+        CorsConfig {
+            allowed_origins: vec![String::from("https://app.example.com")],
+            allowed_methods: vec![String::from("GET"), String::from("POST")],
+            allowed_headers: vec![String::from("content-type")],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preflight_from_allowed_origin() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .wrap(cors(&app_config()))
+                .route("/user", web::post().to(HttpResponse::Created)),
+        )
+        .await;
+        let preflight = |origin: &str, method: &str| {
+            test::TestRequest::default()
+                .method(Method::OPTIONS)
+                .uri("/user")
+                .insert_header((ORIGIN, origin))
+                .insert_header((ACCESS_CONTROL_REQUEST_METHOD, method))
+                .insert_header((ACCESS_CONTROL_REQUEST_HEADERS, "Content-Type"))
+                .to_request()
+        };
+
+        // Act
+        let allowed = test::call_service(&app, preflight("https://app.example.com", "POST")).await;
+        let other_origin =
+            test::call_service(&app, preflight("https://evil.example.com", "POST")).await;
+        let other_method =
+            test::call_service(&app, preflight("https://app.example.com", "DELETE")).await;
+
+        // Assert
+        assert!(allowed.status().is_success());
+        assert_eq!(
+            allowed.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://app.example.com"
+        );
+        assert!(allowed
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_METHODS)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("POST"));
+        assert!(other_origin.status().is_client_error());
+        assert!(other_method.status().is_client_error());
+    }
+
+    #[tokio::test]
+    async fn test_default_config_denies_every_origin() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .wrap(cors(&CorsConfig::default()))
+                .route("/users", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // Act
+        let req = test::TestRequest::get()
+            .uri("/users")
+            .insert_header((ORIGIN, "https://app.example.com"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_permissive_mode_allows_any_origin() {
+        // Arrange
+        let config = CorsConfig {
+            permissive: true,
+            ..Default::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(cors(&config))
+                .route("/users", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // Act
+        let req = test::TestRequest::get()
+            .uri("/users")
+            .insert_header((ORIGIN, "http://localhost:5173"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "http://localhost:5173"
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,4 @@
+pub mod cors;
+pub mod pretty_json;
+pub mod read_only;
+pub mod signature;
\ No newline at end of file
@@ -0,0 +1,103 @@
+use std::{collections::HashMap, env};
+
+use actix_web::{
+    body::{to_bytes, BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    web::Query,
+    Error,
+};
+
+/// Returns `true` when the response for `req` should be pretty-printed.
+///
+/// Pretty-printing is enabled by the `?pretty=true` query parameter, or for every
+/// request when the `APP_ENV` environment variable is set to `dev`.
+fn wants_pretty(req: &ServiceRequest) -> bool {
+    if env::var("APP_ENV").is_ok_and(|app_env| app_env == "dev") {
+        return true;
+    }
+    Query::<HashMap<String, String>>::from_query(req.query_string())
+        .is_ok_and(|query| query.get("pretty").is_some_and(|pretty| pretty == "true"))
+}
+
+/// Middleware that re-indents JSON response bodies when pretty-printing is requested.
+///
+/// Responses that are not `application/json`, or whose body is not valid JSON, are passed
+/// through untouched, so production responses stay minified.
+///
+/// # Errors
+///
+/// Returns an error if the inner service fails or the response body cannot be read.
+pub async fn pretty_json(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let pretty = wants_pretty(&req);
+    let res = next.call(req).await?.map_into_boxed_body();
+    let is_json = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !pretty || !is_json {
+        return Ok(res);
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = to_bytes(body).await.map_err(ErrorInternalServerError)?;
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(value) => serde_json::to_string_pretty(&value)
+            .map(BoxBody::new)
+            .unwrap_or_else(|_| BoxBody::new(bytes)),
+        Err(_) => BoxBody::new(bytes),
+    };
+
+    Ok(ServiceResponse::new(req, res.set_body(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn json_handler() -> HttpResponse {
+        HttpResponse::Ok().json(serde_json::json!({"name": "Test User", "title": "Test Title"}))
+    }
+
+    #[tokio::test]
+    async fn test_pretty_json() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(pretty_json))
+                .route("/user", web::get().to(json_handler)),
+        )
+        .await;
+        let pretty_req = test::TestRequest::get()
+            .uri("/user?pretty=true")
+            .to_request();
+        let compact_req = test::TestRequest::get().uri("/user").to_request();
+
+        // Act
+        let pretty_body = test::call_and_read_body(&app, pretty_req).await;
+        let compact_body = test::call_and_read_body(&app, compact_req).await;
+
+        // Assert
+        let pretty_body = String::from_utf8(pretty_body.to_vec()).unwrap();
+        let compact_body = String::from_utf8(compact_body.to_vec()).unwrap();
+        assert!(
+            pretty_body.contains("\n  \""),
+            "Body is not indented: {}",
+            pretty_body
+        );
+        assert!(
+            !compact_body.contains('\n'),
+            "Body is not compact: {}",
+            compact_body
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,126 @@
+use std::time::SystemTime;
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        header::{HeaderValue, RETRY_AFTER},
+        Method, StatusCode,
+    },
+    middleware::Next,
+    web::Data,
+    Error,
+};
+use arc_swap::ArcSwap;
+
+use crate::{
+    api::api_error::error_response,
+    config::{clock::Clock, runtime_config::RuntimeConfig},
+};
+
+/// Middleware that rejects writes with `503 Service Unavailable` while read-only mode is on.
+///
+/// The flag is read from the shared `RuntimeConfig` on every request, so flipping it through
+/// the config reload endpoint takes effect immediately. Writes are also rejected, with a
+/// `Retry-After` of the seconds left, while the configured maintenance window is open; the
+/// current time comes from the shared `Clock`, or the system clock if none is registered.
+/// Safe methods are always let through, as are the config endpoints so that read-only mode can
+/// be turned off again.
+///
+/// # Errors
+///
+/// Returns an error if the inner service fails.
+pub async fn read_only(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let is_write = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let config = req
+        .app_data::<Data<ArcSwap<RuntimeConfig>>>()
+        .map(|config| config.load_full());
+    if let Some(config) = config.filter(|_| is_write && !req.path().starts_with("/admin/config/")) {
+        if config.read_only {
+            let res = error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "service is in read-only mode",
+            );
+            return Ok(req.into_response(res));
+        }
+        let now = req
+            .app_data::<Data<dyn Clock>>()
+            .map_or_else(SystemTime::now, |clock| clock.now());
+        let remaining = config
+            .maintenance_window
+            .and_then(|window| window.seconds_remaining(now));
+        if let Some(remaining) = remaining {
+            let mut res = error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "service is in a maintenance window",
+            );
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(remaining));
+            return Ok(req.into_response(res));
+        }
+    }
+
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::maintenance_window::MaintenanceWindow;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::{
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    struct FixedClock(SystemTime);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> SystemTime {
+            self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_window_rejects_writes() {
+        // Arrange
+        let config = RuntimeConfig {
+            maintenance_window: Some("02:00-02:30 UTC".parse::<MaintenanceWindow>().unwrap()),
+            ..Default::default()
+        };
+        // 02:10 UTC on the first day after the epoch.
+        let clock: Arc<dyn Clock> = Arc::new(FixedClock(
+            UNIX_EPOCH + Duration::from_secs(24 * 3600 + 2 * 3600 + 10 * 60),
+        ));
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(read_only))
+                .app_data(Data::new(ArcSwap::from_pointee(config)))
+                .app_data(Data::from(clock))
+                .route("/user", web::post().to(HttpResponse::Ok))
+                .route("/users", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // Act
+        let write =
+            test::call_service(&app, test::TestRequest::post().uri("/user").to_request()).await;
+        let read =
+            test::call_service(&app, test::TestRequest::get().uri("/users").to_request()).await;
+
+        // Assert
+        assert_eq!(write.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            write
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok()),
+            Some("1200")
+        );
+        assert_eq!(read.status(), StatusCode::OK);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,209 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::PayloadError,
+    http::StatusCode,
+    middleware::Next,
+    web::{Bytes, Data},
+    Error,
+};
+use futures::stream;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{api::api_error::error_response, config::signing_config::SigningConfig};
+
+/// Header carrying the hex-encoded HMAC-SHA256 signature of the request.
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Header carrying the Unix time, in seconds, at which the request was signed.
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+
+/// Paths that are served without a signature, such as probes that cannot sign requests.
+const UNSIGNED_PATHS: [&str; 3] = ["/health", "/livez", "/readyz"];
+
+/// How far, in seconds, a request's timestamp may be from the server clock.
+const TIMESTAMP_TOLERANCE_SECS: u64 = 5 * 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Builds the HMAC over the signed parts of a request.
+///
+/// The message is the timestamp, method and path (including the query string), separated by
+/// newlines and followed by a newline and the raw body.
+fn request_mac(
+    secret: &[u8],
+    timestamp: &str,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}\n", timestamp, method, path).as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// Returns `true` when `timestamp` is within the tolerance window around the current time.
+fn is_fresh(timestamp: &str) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+    timestamp
+        .parse::<u64>()
+        .is_ok_and(|timestamp| now.abs_diff(timestamp) <= TIMESTAMP_TOLERANCE_SECS)
+}
+
+/// Middleware that rejects requests without a valid HMAC signature with `401 Unauthorized`.
+///
+/// Only active when the shared `SigningConfig` has a secret, and never for `UNSIGNED_PATHS`.
+/// Callers send the signature in `X-Signature` and the signing time in `X-Timestamp`; requests
+/// signed more than five minutes away from the server clock are rejected so captured requests
+/// cannot be replayed later.
+///
+/// # Errors
+///
+/// Returns an error if the request body cannot be read or the inner service fails.
+pub async fn verify_signature(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let secret = req
+        .app_data::<Data<SigningConfig>>()
+        .and_then(|config| config.hmac_secret.clone());
+    let Some(secret) = secret.filter(|_| !UNSIGNED_PATHS.contains(&req.path())) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    let (Some(signature), Some(timestamp)) = (header(SIGNATURE_HEADER), header(TIMESTAMP_HEADER))
+    else {
+        let res = error_response(StatusCode::UNAUTHORIZED, "missing request signature");
+        return Ok(req.into_response(res));
+    };
+    if !is_fresh(&timestamp) {
+        let res = error_response(
+            StatusCode::UNAUTHORIZED,
+            "request timestamp is outside the allowed window",
+        );
+        return Ok(req.into_response(res));
+    }
+
+    let body = req.extract::<Bytes>().await?;
+    let path = req
+        .uri()
+        .path_and_query()
+        .map_or_else(|| req.path().to_owned(), |path| path.as_str().to_owned());
+    let mac = request_mac(
+        secret.as_bytes(),
+        &timestamp,
+        req.method().as_str(),
+        &path,
+        &body,
+    );
+    let verified =
+        hex::decode(signature).is_ok_and(|signature| mac.verify_slice(&signature).is_ok());
+    if !verified {
+        let res = error_response(StatusCode::UNAUTHORIZED, "invalid request signature");
+        return Ok(req.into_response(res));
+    }
+
+    let body = stream::once(async move { Ok::<_, PayloadError>(body) });
+    req.set_payload(Payload::Stream {
+        payload: Box::pin(body),
+    });
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn echo_handler(body: Bytes) -> HttpResponse {
+        HttpResponse::Ok().body(body)
+    }
+
+    /// Signs a request the way `verify_signature` expects, returning the hex-encoded signature.
+    fn sign_request(
+        secret: &str,
+        timestamp: &str,
+        method: &str,
+        path: &str,
+        body: &[u8],
+    ) -> String {
+        hex::encode(
+            request_mac(secret.as_bytes(), timestamp, method, path, body)
+                .finalize()
+                .into_bytes(),
+        )
+    }
+
+    fn now() -> String {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_verify_signature() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(verify_signature))
+                .app_data(Data::new(SigningConfig {
+                    hmac_secret: Some(String::from("test-secret")),
+                }))
+                .route("/user", web::post().to(echo_handler))
+                .route("/health", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let timestamp = now();
+        let body = r#"{"name":"Signed User"}"#;
+        let signature = sign_request("test-secret", &timestamp, "POST", "/user", body.as_bytes());
+        let signed_req = test::TestRequest::post()
+            .uri("/user")
+            .insert_header((SIGNATURE_HEADER, signature.clone()))
+            .insert_header((TIMESTAMP_HEADER, timestamp.clone()))
+            .set_payload(body)
+            .to_request();
+        let tampered_req = test::TestRequest::post()
+            .uri("/user")
+            .insert_header((SIGNATURE_HEADER, signature.clone()))
+            .insert_header((TIMESTAMP_HEADER, timestamp))
+            .set_payload(r#"{"name":"Tampered User"}"#)
+            .to_request();
+        let stale_req = test::TestRequest::post()
+            .uri("/user")
+            .insert_header((SIGNATURE_HEADER, signature))
+            .insert_header((TIMESTAMP_HEADER, "0"))
+            .set_payload(body)
+            .to_request();
+
+        // Act
+        let signed_resp = test::call_service(&app, signed_req).await;
+        let signed_status = signed_resp.status();
+        let signed_body = test::read_body(signed_resp).await;
+        let tampered_resp = test::call_service(&app, tampered_req).await;
+        let stale_resp = test::call_service(&app, stale_req).await;
+        let health_req = test::TestRequest::get().uri("/health").to_request();
+        let health_resp = test::call_service(&app, health_req).await;
+
+        // Assert
+        assert_eq!(signed_status, StatusCode::OK);
+        assert_eq!(signed_body, body.as_bytes());
+        assert_eq!(tampered_resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(stale_resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(health_resp.status(), StatusCode::OK);
+    }
+}
\ No newline at end of file
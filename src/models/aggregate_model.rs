@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::user_model::User;
+
+/// Distinct values available to the user filter UI.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct FilterOptions {
+    /// The distinct user locations, sorted ascending.
+    pub locations: Vec<String>,
+    /// The distinct user titles, sorted ascending.
+    pub titles: Vec<String>,
+}
+
+/// Number of users holding a given title.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TitleCount {
+    /// The user title.
+    pub title: String,
+    /// The number of users with this title.
+    pub count: i64,
+}
+
+/// Title distribution of the users in one location.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LocationTitleCounts {
+    /// The user location.
+    pub location: String,
+    /// The total number of users in this location.
+    pub total: i64,
+    /// The number of users per title in this location, sorted by title.
+    pub titles: Vec<TitleCount>,
+}
+
+/// One page of users together with the number of users matching the query across all pages.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UserPage {
+    /// The users on this page.
+    pub users: Vec<User>,
+    /// The number of users matching the query, ignoring paging.
+    pub total: i64,
+}
\ No newline at end of file
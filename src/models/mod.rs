@@ -1 +1,3 @@
+pub mod aggregate_model;
+pub mod scroll_model;
 pub mod user_model;
\ No newline at end of file
@@ -0,0 +1,28 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::models::user_model::User;
+
+/// Server-side position of a resumable scroll over the users.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScrollState {
+    /// The scroll ID handed out to the client.
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// The ID of the last user returned so far, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_id: Option<ObjectId>,
+    /// When the scroll is forgotten unless it is resumed before then.
+    pub expires_at: DateTime,
+}
+
+/// One batch of a resumable scroll.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScrollPage {
+    /// The ID to pass back to continue the scroll.
+    pub scroll_id: String,
+    /// The users in this batch, in ascending `_id` order.
+    pub users: Vec<User>,
+    /// Whether the scroll has reached the end of the collection.
+    pub done: bool,
+}
\ No newline at end of file
@@ -1,16 +1,155 @@
-use mongodb::bson::oid::ObjectId;
-use serde::{Serialize, Deserialize};
+use std::borrow::Cow;
+
+use mongodb::bson::{oid::ObjectId, DateTime, Document};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use validator::{Validate, ValidationError};
+
+/// Rejects a `location` that is empty or only whitespace.
+fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message(Cow::from("must not be empty")));
+    }
+    Ok(())
+}
 
 /// Represents a user entity.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+///
+/// Clients' payloads are checked with `Validate`: `name` and `title` must be 1 to 120 characters
+/// long and `location` must not be blank.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Validate)]
 pub struct User {
     /// The unique identifier of the user.
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     /// The name of the user.
+    #[validate(length(min = 1, max = 120, message = "must be between 1 and 120 characters"))]
+    pub name: String,
+    /// The location of the user.
+    #[serde(default)]
+    #[validate(custom(function = "not_blank"))]
+    pub location: String,
+    /// The title of the user.
+    #[validate(length(min = 1, max = 120, message = "must be between 1 and 120 characters"))]
+    pub title: String,
+    /// The email address of the user, unique across users when not empty.
+    #[serde(default)]
+    pub email: String,
+    /// Free-form UI preferences of the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferences: Option<Document>,
+    /// When the user was last seen active.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime>,
+    /// The worker currently holding an exclusive claim on the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_by: Option<String>,
+    /// When the current claim on the user expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<DateTime>,
+    /// The user who invited this user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referred_by: Option<ObjectId>,
+    /// Human-friendly sequential number of the user. Set by the repository on create.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_number: Option<i64>,
+    /// When the user was created. Set by the repository; values sent by clients are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime>,
+    /// When the user was last created, updated or patched. Set by the repository; values sent by
+    /// clients are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime>,
+}
+
+/// A partial update to a user; only the fields that are `Some` are changed.
+///
+/// The fields that are set are validated with the same rules as `User`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Validate)]
+pub struct UserPatch {
+    /// The new name of the user.
+    #[validate(length(min = 1, max = 120, message = "must be between 1 and 120 characters"))]
+    pub name: Option<String>,
+    /// The new location of the user.
+    #[validate(custom(function = "not_blank"))]
+    pub location: Option<String>,
+    /// The new title of the user.
+    #[validate(length(min = 1, max = 120, message = "must be between 1 and 120 characters"))]
+    pub title: Option<String>,
+}
+
+/// A user as returned by the API, with ObjectIds rendered as plain hex strings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UserResponse {
+    /// The unique identifier of the user, as a hex string.
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// The name of the user.
     pub name: String,
     /// The location of the user.
     pub location: String,
     /// The title of the user.
     pub title: String,
+    /// The email address of the user.
+    #[serde(default)]
+    pub email: String,
+    /// Free-form UI preferences of the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferences: Option<Document>,
+    /// When the user was last seen active.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime>,
+    /// The worker currently holding an exclusive claim on the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_by: Option<String>,
+    /// When the current claim on the user expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<DateTime>,
+    /// The ID of the user who invited this user, as a hex string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referred_by: Option<String>,
+    /// Human-friendly sequential number of the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_number: Option<i64>,
+    /// When the user was created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime>,
+    /// When the user was last created, updated or patched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        UserResponse {
+            id: user.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: user.name,
+            location: user.location,
+            title: user.title,
+            email: user.email,
+            preferences: user.preferences,
+            last_seen: user.last_seen,
+            locked_by: user.locked_by,
+            locked_until: user.locked_until,
+            referred_by: user.referred_by.map(|id| id.to_hex()),
+            member_number: user.member_number,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+impl User {
+    /// Computes a stable hash of the user's meaningful content.
+    ///
+    /// Only `name`, `location`, `title` and `email` are hashed, so bookkeeping fields such as
+    /// `last_seen` or claim state never change the result.
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for field in [&self.name, &self.location, &self.title, &self.email] {
+            hasher.update(field.as_bytes());
+            hasher.update([0]);
+        }
+        hex::encode(hasher.finalize())
+    }
 }
\ No newline at end of file
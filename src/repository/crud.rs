@@ -0,0 +1,58 @@
+use std::future::Future;
+
+use mongodb::results::{DeleteResult, InsertOneResult, UpdateResult};
+
+use crate::repository::error::RepoError;
+
+/// Basic CRUD operations over documents of type `T`, keyed by their ObjectId.
+///
+/// `MongoRepo<T>` implements this for any serializable model, so a new collection only needs a
+/// model type rather than its own copy of the CRUD logic.
+// Users go through the richer methods on `MongoRepo<User>`, so some of these only have callers
+// for other models.
+#[allow(dead_code)]
+pub trait Repository<T> {
+    /// Inserts `item` as a new document.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if the insert fails.
+    fn create(&self, item: T) -> impl Future<Output = Result<InsertOneResult, RepoError>> + Send;
+
+    /// Retrieves the document with the given ID, or `None` if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::InvalidObjectId` if `id` is not a valid ObjectId, or
+    /// `RepoError::Database` if the query fails.
+    fn find_by_id(&self, id: &str) -> impl Future<Output = Result<Option<T>, RepoError>> + Send;
+
+    /// Replaces the document with the given ID by `item`.
+    ///
+    /// The whole document is replaced, so `item` must not carry a different `_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::InvalidObjectId` if `id` is not a valid ObjectId, `RepoError::NotFound`
+    /// if no document has that ID, or `RepoError::Database` if the replacement fails.
+    fn update(
+        &self,
+        id: &str,
+        item: T,
+    ) -> impl Future<Output = Result<UpdateResult, RepoError>> + Send;
+
+    /// Deletes the document with the given ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::InvalidObjectId` if `id` is not a valid ObjectId, `RepoError::NotFound`
+    /// if no document has that ID, or `RepoError::Database` if the delete fails.
+    fn delete(&self, id: &str) -> impl Future<Output = Result<DeleteResult, RepoError>> + Send;
+
+    /// Retrieves every document in the collection.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if the query fails or a document cannot be deserialized.
+    fn list(&self) -> impl Future<Output = Result<Vec<T>, RepoError>> + Send;
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use mongodb::{
+    bson::{self, oid::ObjectId},
+    options::ReadConcern,
+};
+
+/// Errors returned by repository methods that validate their input before querying MongoDB.
+#[derive(Debug)]
+pub enum RepoError {
+    /// No user matched the given ID.
+    NotFound,
+    /// The given ID is not a valid ObjectId hex string.
+    InvalidObjectId(String),
+    /// The requested field is not one users can be sorted by.
+    InvalidSortField(String),
+    /// A location filter was empty.
+    EmptyLocation,
+    /// A partial update did not set any field.
+    EmptyPatch,
+    /// Another user already has the given email.
+    DuplicateEmail,
+    /// A bulk insert was given no users.
+    EmptyBatch,
+    /// An operation keyed by email was given an empty email.
+    EmptyEmail,
+    /// The requested read concern level is not one clients may choose.
+    InvalidReadConcern(String),
+    /// A required environment variable is not set.
+    MissingConfig(&'static str),
+    /// MongoDB failed or rejected the operation.
+    Database(mongodb::error::Error),
+}
+
+impl fmt::Display for RepoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepoError::NotFound => write!(f, "no user found with the specified id"),
+            RepoError::InvalidObjectId(id) => write!(f, "invalid object id '{}'", id),
+            RepoError::InvalidSortField(field) => write!(
+                f,
+                "cannot sort users by '{}'; expected one of name, location, title",
+                field
+            ),
+            RepoError::EmptyLocation => write!(f, "location must not be empty"),
+            RepoError::EmptyPatch => {
+                write!(f, "patch must set at least one of name, location, title")
+            }
+            RepoError::DuplicateEmail => write!(f, "a user with this email already exists"),
+            RepoError::EmptyBatch => write!(f, "at least one user is required"),
+            RepoError::EmptyEmail => write!(f, "email must not be empty"),
+            RepoError::InvalidReadConcern(level) => write!(
+                f,
+                "invalid read concern '{}'; expected one of local, majority, available",
+                level
+            ),
+            RepoError::MissingConfig(name) => {
+                write!(f, "the {} environment variable must be set", name)
+            }
+            RepoError::Database(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for RepoError {}
+
+impl From<mongodb::error::Error> for RepoError {
+    fn from(err: mongodb::error::Error) -> Self {
+        RepoError::Database(err)
+    }
+}
+
+impl From<bson::de::Error> for RepoError {
+    fn from(err: bson::de::Error) -> Self {
+        RepoError::Database(err.into())
+    }
+}
+
+impl From<bson::ser::Error> for RepoError {
+    fn from(err: bson::ser::Error) -> Self {
+        RepoError::Database(err.into())
+    }
+}
+
+/// Parses `id` as an ObjectId hex string.
+///
+/// # Errors
+///
+/// Returns `RepoError::InvalidObjectId` if `id` is not a 24-character hex string.
+pub fn parse_object_id(id: &str) -> Result<ObjectId, RepoError> {
+    ObjectId::parse_str(id).map_err(|_| RepoError::InvalidObjectId(id.to_owned()))
+}
+
+/// Parses `level` as a read concern clients may request.
+///
+/// # Errors
+///
+/// Returns `RepoError::InvalidReadConcern` unless `level` is `local`, `majority` or `available`.
+pub fn parse_read_concern(level: &str) -> Result<ReadConcern, RepoError> {
+    match level {
+        "local" => Ok(ReadConcern::local()),
+        "majority" => Ok(ReadConcern::majority()),
+        "available" => Ok(ReadConcern::available()),
+        _ => Err(RepoError::InvalidReadConcern(level.to_owned())),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,70 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex, PoisonError,
+};
+
+use mongodb::bson::DateTime;
+
+/// Cache of the newest `updated_at` in a collection.
+///
+/// Every write bumps a generation counter, which invalidates the cached value. A value computed
+/// while a write was in progress is stored under the generation read before the query, so it is
+/// never served once that write has finished.
+#[derive(Debug, Default)]
+pub struct LastModifiedCache {
+    generation: AtomicU64,
+    cached: Mutex<Option<(u64, Option<DateTime>)>>,
+}
+
+impl LastModifiedCache {
+    /// Returns the cached timestamp, or `None` when it has to be recomputed.
+    pub fn get(&self) -> Option<Option<DateTime>> {
+        let generation = self.generation();
+        match *self.cached.lock().unwrap_or_else(PoisonError::into_inner) {
+            Some((cached, last_modified)) if cached == generation => Some(last_modified),
+            _ => None,
+        }
+    }
+
+    /// Returns the current generation; read it before computing a value to `store`.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Caches `last_modified` as computed at `generation`.
+    pub fn store(&self, generation: u64, last_modified: Option<DateTime>) {
+        *self.cached.lock().unwrap_or_else(PoisonError::into_inner) =
+            Some((generation, last_modified));
+    }
+
+    /// Drops the cached value. Called after every write.
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalidate_drops_cached_value() {
+        // Arrange
+        let cache = LastModifiedCache::default();
+        let now = DateTime::now();
+        let stale_generation = cache.generation();
+        cache.store(stale_generation, Some(now));
+        let cached = cache.get();
+
+        // Act
+        cache.invalidate();
+        let after_write = cache.get();
+        cache.store(stale_generation, None);
+        let stale = cache.get();
+
+        // Assert
+        assert_eq!(cached, Some(Some(now)));
+        assert_eq!(after_write, None);
+        assert_eq!(stale, None);
+    }
+}
\ No newline at end of file
@@ -1 +1,5 @@
-pub mod mongodb_repo;
\ No newline at end of file
+pub mod crud;
+pub mod error;
+pub mod last_modified;
+pub mod mongodb_repo;
+pub mod repo_metrics;
\ No newline at end of file
@@ -1,85 +1,793 @@
-use std::env;
+use std::{
+    env, future::Future, io::Cursor, marker::PhantomData, str::FromStr, sync::Arc, sync::OnceLock,
+    time::Duration,
+};
 extern crate dotenv;
 
+use arc_swap::ArcSwap;
 use dotenv::dotenv;
 
-use futures::stream::TryStreamExt;
+use futures::stream::{Stream, TryStreamExt};
 use mongodb::{
-    bson::{doc, extjson::de::Error, oid::ObjectId},
+    bson::{doc, from_document, oid::ObjectId, Bson, DateTime, Document},
+    error::{ErrorKind, WriteFailure},
+    options::{
+        ClientOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions,
+        InsertManyOptions, ReadConcern, ReplaceOptions, ReturnDocument, UpdateOptions,
+    },
     results::{DeleteResult, InsertOneResult, UpdateResult},
-    Client, Collection,
+    Client, Collection, IndexModel,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    models::{
+        aggregate_model::{FilterOptions, LocationTitleCounts, UserPage},
+        scroll_model::{ScrollPage, ScrollState},
+        user_model::{User, UserPatch},
+    },
+    repository::{
+        crud::Repository,
+        error::{parse_object_id, RepoError},
+        last_modified::LastModifiedCache,
+        repo_metrics::{Operation, RepoMetrics, RepoMetricsSnapshot},
+    },
 };
 
-use crate::models::user_model::User;
+/// Fields `get_all_users_sorted` accepts as a sort key.
+const SORTABLE_FIELDS: [&str; 3] = ["name", "location", "title"];
+
+/// Name of the database the repository works in after `init` when `DB_NAME` is unset.
+const DEFAULT_DB_NAME: &str = "rustDB";
+
+/// Name of the user collection when `COLLECTION_NAME` is unset.
+const DEFAULT_COLLECTION_NAME: &str = "User";
+
+/// Name of the unique index on `email`.
+const EMAIL_INDEX: &str = "email";
+
+/// How long a scroll survives without being resumed.
+const SCROLL_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Total attempts for a read when `READ_RETRY_ATTEMPTS` is unset.
+const DEFAULT_READ_RETRY_ATTEMPTS: u32 = 3;
+
+/// Returns how many times a read is attempted before its error is surfaced.
+///
+/// Read once from `READ_RETRY_ATTEMPTS`; values below one are treated as one.
+fn read_retry_attempts() -> u32 {
+    static ATTEMPTS: OnceLock<u32> = OnceLock::new();
+    *ATTEMPTS.get_or_init(|| {
+        env::var("READ_RETRY_ATTEMPTS")
+            .ok()
+            .and_then(|attempts| attempts.parse().ok())
+            .unwrap_or(DEFAULT_READ_RETRY_ATTEMPTS)
+            .max(1)
+    })
+}
+
+/// Parses a cursor batch size, rejecting anything that is not a positive integer.
+fn parse_batch_size(value: Option<&str>) -> Option<u32> {
+    value
+        .and_then(|size| size.trim().parse().ok())
+        .filter(|&size| size > 0)
+}
+
+/// Returns how many documents list and export cursors fetch per round trip.
+///
+/// Read once from `MONGO_BATCH_SIZE`; `None` when unset or invalid, which keeps the driver default.
+fn batch_size() -> Option<u32> {
+    static BATCH_SIZE: OnceLock<Option<u32>> = OnceLock::new();
+    *BATCH_SIZE.get_or_init(|| parse_batch_size(env::var("MONGO_BATCH_SIZE").ok().as_deref()))
+}
+
+/// Returns `true` for errors that say nothing about the query itself, such as a dropped connection.
+fn is_transient(err: &mongodb::error::Error) -> bool {
+    matches!(
+        err.kind.as_ref(),
+        ErrorKind::Io(_)
+            | ErrorKind::ServerSelection { .. }
+            | ErrorKind::ConnectionPoolCleared { .. }
+    )
+}
+
+/// Runs `read` up to `attempts` times, retrying only while it fails with a transient error.
+///
+/// Only use this for reads: retrying a write after an ambiguous failure could apply it twice.
+async fn retry_read<T, F, Fut>(attempts: u32, mut read: F) -> mongodb::error::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = mongodb::error::Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match read().await {
+            Err(err) if attempt < attempts && is_transient(&err) => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+/// Escapes regex metacharacters in `text` so it matches literally inside a `$regex` filter.
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#-".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Returns `true` when a write error with `code` and `message` violated the unique email index.
+fn violates_email_index(code: i32, message: &str) -> bool {
+    code == 11000 && message.contains(&format!("index: {} ", EMAIL_INDEX))
+}
+
+/// Reads the connection string from `MONGOURI`, loading `.env` first.
+fn mongo_uri() -> Result<String, RepoError> {
+    dotenv().ok();
+    env::var("MONGOURI").map_err(|_| RepoError::MissingConfig("MONGOURI"))
+}
+
+/// Parses the connection setting `name` as returned by `lookup`.
+///
+/// Returns `None` when the setting is unset, and also when it is not a valid number, in which case
+/// a warning is logged and the driver default is kept.
+fn parse_setting<T: FromStr>(name: &str, lookup: impl Fn(&str) -> Option<String>) -> Option<T> {
+    let value = lookup(name)?;
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        eprintln!(
+            "Ignoring {}={:?}: not a valid number, using the driver default",
+            name, value
+        );
+    }
+    parsed
+}
+
+/// Applies `MONGO_MAX_POOL`, `MONGO_MIN_POOL` and `MONGO_CONNECT_TIMEOUT_MS`, as returned by
+/// `lookup`, on top of the options parsed from the URI.
+fn apply_pool_settings(options: &mut ClientOptions, lookup: impl Fn(&str) -> Option<String>) {
+    if let Some(max_pool_size) = parse_setting("MONGO_MAX_POOL", &lookup) {
+        options.max_pool_size = Some(max_pool_size);
+    }
+    if let Some(min_pool_size) = parse_setting("MONGO_MIN_POOL", &lookup) {
+        options.min_pool_size = Some(min_pool_size);
+    }
+    if let Some(timeout_ms) = parse_setting("MONGO_CONNECT_TIMEOUT_MS", &lookup) {
+        options.connect_timeout = Some(Duration::from_millis(timeout_ms));
+    }
+}
+
+/// Builds a client for `uri` with the pool settings from the environment applied.
+async fn build_client(uri: &str) -> Result<Client, RepoError> {
+    let mut options = ClientOptions::parse(uri).await?;
+    apply_pool_settings(&mut options, |name| env::var(name).ok());
+    Ok(Client::with_options(options)?)
+}
+
+/// Maps a write error to `RepoError::DuplicateEmail` when it violated the unique email index.
+///
+/// Any other error, including a duplicate `_id`, is returned unchanged.
+fn email_conflict(err: impl Into<RepoError>) -> RepoError {
+    let err = match err.into() {
+        RepoError::Database(err) => err,
+        err => return err,
+    };
+    let duplicate_email = match err.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) => {
+            violates_email_index(write_error.code, &write_error.message)
+        }
+        ErrorKind::BulkWrite(failure) => failure
+            .write_errors
+            .iter()
+            .flatten()
+            .any(|write_error| violates_email_index(write_error.code, &write_error.message)),
+        _ => false,
+    };
+    if duplicate_email {
+        RepoError::DuplicateEmail
+    } else {
+        RepoError::Database(err)
+    }
+}
+
+/// A client together with the name of the database the repository works in.
+struct Connection {
+    client: Client,
+    db_name: String,
+}
+
+impl Connection {
+    /// Wraps `client`, using the database named in its URI or `fallback_db` if it names none.
+    fn new(client: Client, fallback_db: &str) -> Self {
+        let db_name = client
+            .default_database()
+            .map_or_else(|| fallback_db.to_owned(), |db| db.name().to_owned());
+        Connection { client, db_name }
+    }
+}
+
+/// Builds the find options for one page of users.
+///
+/// A `limit` of zero or less means no limit, and a `read_concern` of `None` keeps the
+/// collection default. The cursor batch size comes from `MONGO_BATCH_SIZE`.
+pub fn page_options(skip: u64, limit: i64, read_concern: Option<ReadConcern>) -> FindOptions {
+    let mut options = FindOptions::builder()
+        .skip(skip)
+        .limit((limit > 0).then_some(limit))
+        .build();
+    options.read_concern = read_concern;
+    options.batch_size = batch_size();
+    options
+}
+
+/// MongoDB-backed storage for documents of type `T`, users by default.
+pub struct MongoRepo<T = User> {
+    conn: ArcSwap<Connection>,
+    collection_name: String,
+    metrics: RepoMetrics,
+    last_modified_cache: LastModifiedCache,
+    model: PhantomData<fn() -> T>,
+}
+
+impl<T> MongoRepo<T> {
+    /// Builds a repository on an existing `Client`, storing documents in `collection`.
+    ///
+    /// The driver pools connections per `Client`, so repositories for different collections
+    /// should share one. Unlike `MongoRepo::init`, no indexes are created, so this works for any
+    /// model.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client to send operations through.
+    /// * `db_name` - The database to work in.
+    /// * `collection` - The collection documents are stored in.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::Client;
+    /// # use your_project_name::repository::MongoRepo;
+    /// # fn example_function(client: Client) {
+    /// let products: MongoRepo<Product> = MongoRepo::with_client(client, "rustDB", "Product");
+    /// # }
+    /// ```
+    pub fn with_client(client: Client, db_name: &str, collection: &str) -> Self {
+        MongoRepo {
+            conn: ArcSwap::from_pointee(Connection {
+                client,
+                db_name: db_name.to_owned(),
+            }),
+            collection_name: collection.to_owned(),
+            metrics: RepoMetrics::default(),
+            last_modified_cache: LastModifiedCache::default(),
+            model: PhantomData,
+        }
+    }
+
+    /// Awaits a database operation, counting it in `metrics`.
+    ///
+    /// Writes drop the cached `last_modified` once they finish, whether or not they succeeded.
+    async fn track<R, E>(
+        &self,
+        kind: Operation,
+        operation: impl Future<Output = Result<R, E>>,
+    ) -> Result<R, E> {
+        let result = self.metrics.track(kind, operation).await;
+        if matches!(
+            kind,
+            Operation::Insert | Operation::Update | Operation::Delete
+        ) {
+            self.last_modified_cache.invalidate();
+        }
+        result
+    }
+
+    /// Derives the model's collection from the current connection.
+    fn col(&self) -> Collection<T> {
+        let conn = self.conn.load();
+        conn.client
+            .database(&conn.db_name)
+            .collection(&self.collection_name)
+    }
+
+    /// Checks that the database is reachable by sending it a `ping` command.
+    ///
+    /// The command runs against the database itself, so no collection is read.
+    ///
+    /// # Returns
+    ///
+    /// An empty `Result` if the server answered, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if no server can be reached or the command fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// repo.ping().await?;
+    /// println!("Database is up");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn ping(&self) -> Result<(), RepoError> {
+        let conn = self.conn.load();
+        conn.client
+            .database(&conn.db_name)
+            .run_command(doc! {"ping": 1}, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the name of the database the repository currently targets.
+    pub fn database_name(&self) -> String {
+        self.conn.load().db_name.clone()
+    }
+}
+
+impl<T> Repository<T> for MongoRepo<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + Unpin,
+{
+    async fn create(&self, item: T) -> Result<InsertOneResult, RepoError> {
+        let col = self.col();
+        let insert = col.insert_one(item, None);
+        self.track(Operation::Insert, insert)
+            .await
+            .map_err(RepoError::from)
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<T>, RepoError> {
+        let filter = doc! {"_id": parse_object_id(id)?};
+        let col = self.col();
+        retry_read(read_retry_attempts(), || {
+            self.track(Operation::Find, col.find_one(filter.clone(), None))
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    async fn update(&self, id: &str, item: T) -> Result<UpdateResult, RepoError> {
+        let filter = doc! {"_id": parse_object_id(id)?};
+        let col = self.col();
+        let replace = col.replace_one(filter, item, None);
+        let result = self.track(Operation::Update, replace).await?;
+        if result.matched_count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(result)
+    }
+
+    async fn delete(&self, id: &str) -> Result<DeleteResult, RepoError> {
+        let filter = doc! {"_id": parse_object_id(id)?};
+        let col = self.col();
+        let delete = col.delete_one(filter, None);
+        let result = self.track(Operation::Delete, delete).await?;
+        if result.deleted_count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(result)
+    }
 
-pub struct MongoRepo {
-    col: Collection<User>,
+    async fn list(&self) -> Result<Vec<T>, RepoError> {
+        let col = &self.col();
+        let metrics = &self.metrics;
+        retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(None, page_options(0, 0, None)))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
 }
 
 impl MongoRepo {
     /// Initializes the MongoDB repository.
     ///
+    /// The database and user collection are read from `DB_NAME` and `COLLECTION_NAME`, falling
+    /// back to `rustDB` and `User`.
+    ///
     /// # Returns
     ///
-    /// The initialized `MongoRepo` instance.
+    /// A `Result` containing the initialized `MongoRepo` instance, or a `RepoError` if an error occurs.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if there is an error connecting to the database or loading environment variables.
+    /// Returns `RepoError::MissingConfig` if `MONGOURI` is not set, or `RepoError::Database` if
+    /// there is an error connecting to the database or creating the indexes.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// # use your_project_name::repository::MongoRepo;
-    /// # async fn example_function() {
-    /// let repo = MongoRepo::init().await;
+    /// # use your_project_name::repository::{error::RepoError, MongoRepo};
+    /// # async fn example_function() -> Result<(), RepoError> {
+    /// let repo = MongoRepo::init().await?;
     /// println!("MongoDB repository initialized successfully.");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn init() -> Result<Self, RepoError> {
+        let client = build_client(&mongo_uri()?).await?;
+        let db_name = env::var("DB_NAME").unwrap_or_else(|_| String::from(DEFAULT_DB_NAME));
+        let repo = Self::from_client(client, &db_name);
+        repo.ensure_indexes().await?;
+        Ok(repo)
+    }
+
+    /// Builds the user repository on an existing `Client`.
+    ///
+    /// Users are stored in the collection named by `COLLECTION_NAME`, falling back to `User`.
+    /// Unlike `init`, no indexes are created; call `ensure_indexes` if the collection is new.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client to send operations through, typically shared with other
+    ///   repositories.
+    /// * `db_name` - The database to work in.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::Client;
+    /// # use your_project_name::repository::MongoRepo;
+    /// # fn example_function(client: Client) {
+    /// let users = MongoRepo::from_client(client.clone(), "rustDB");
+    /// let products: MongoRepo<Product> = MongoRepo::with_client(client, "rustDB", "Product");
     /// # }
     /// ```
-    pub async fn init() -> Self {
+    pub fn from_client(client: Client, db_name: &str) -> Self {
         dotenv().ok();
-        let uri = env::var("MONGOURI").expect("MONGOURI environment variable not set");
-        let client = Client::with_uri_str(&uri).await.expect("Error connecting to database");
-        let db = client.database("rustDB");
-        let col: Collection<User> = db.collection("User");
-        MongoRepo { col }
+        let collection =
+            env::var("COLLECTION_NAME").unwrap_or_else(|_| String::from(DEFAULT_COLLECTION_NAME));
+        Self::with_client(client, db_name, &collection)
     }
 
-    /// Creates a new user in the database asynchronously.
+    /// Initializes the MongoDB repository against the given database and user collection.
+    ///
+    /// Unlike `init`, this ignores `DB_NAME` and `COLLECTION_NAME`, so tests can use an isolated
+    /// collection without touching the environment. Only built for tests.
     ///
     /// # Arguments
     ///
-    /// * `new_user` - The user object to be created.
+    /// * `db_name` - The database to work in.
+    /// * `collection` - The collection users are stored in.
     ///
     /// # Returns
     ///
-    /// A `Result` containing an `InsertOneResult` if successful, or an `Error` if an error occurs.
+    /// A `Result` containing the initialized `MongoRepo` instance, or a `RepoError` if an error occurs.
     ///
     /// # Errors
     ///
-    /// This function may return an error if there is an issue with creating the user in the database.
+    /// Returns `RepoError::MissingConfig` if `MONGOURI` is not set, or `RepoError::Database` if
+    /// there is an error connecting to the database or creating the indexes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, MongoRepo};
+    /// # async fn example_function() -> Result<(), RepoError> {
+    /// let repo = MongoRepo::init_with_names("rustDB_test", "User_test").await?;
+    /// println!("Using {}", repo.database_name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(test)]
+    pub async fn init_with_names(db_name: &str, collection: &str) -> Result<Self, RepoError> {
+        let client = build_client(&mongo_uri()?).await?;
+        let repo = Self::with_client(client, db_name, collection);
+        repo.ensure_indexes().await?;
+        Ok(repo)
+    }
+
+    /// Derives the scroll state collection from the current connection.
+    fn scroll_col(&self) -> Collection<ScrollState> {
+        let conn = self.conn.load();
+        conn.client.database(&conn.db_name).collection("Scroll")
+    }
+
+    /// Derives the sequence counter collection from the current connection.
+    fn counters_col(&self) -> Collection<Document> {
+        let conn = self.conn.load();
+        conn.client.database(&conn.db_name).collection("counters")
+    }
+
+    /// Replaces the underlying client with one connected to `new_uri`.
+    ///
+    /// The new client and its collection are swapped in atomically, so in-flight operations finish
+    /// against the old connection while every later operation uses the new one. The database named
+    /// in `new_uri` is used, falling back to the current database when the URI names none.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_uri` - The MongoDB connection string to switch to.
+    ///
+    /// # Returns
+    ///
+    /// An empty `Result` if successful, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if `new_uri` is invalid or the indexes cannot be created
+    /// on the new database. The previous connection stays in place in that case.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// # use crate::models::User;
     /// # use mongodb::error::Error;
-    /// # use mongodb::results::InsertOneResult;
     /// # use your_project_name::repository::YourRepository;
     /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// repo.reconnect("mongodb://localhost:27017/otherDB").await?;
+    /// println!("Now using {}", repo.database_name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn reconnect(&self, new_uri: &str) -> Result<(), RepoError> {
+        let client = build_client(new_uri).await?;
+        let fallback_db = self.database_name();
+        let old = self
+            .conn
+            .swap(Arc::new(Connection::new(client, &fallback_db)));
+        self.last_modified_cache.invalidate();
+        if let Err(err) = self.ensure_indexes().await {
+            self.conn.store(old);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Creates the indexes the repository relies on, if they do not exist yet.
+    ///
+    /// # Returns
+    ///
+    /// An empty `Result` if successful, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with creating an index in the database.
+    pub async fn ensure_indexes(&self) -> Result<(), RepoError> {
+        let name_location = IndexModel::builder()
+            .keys(doc! {"name": 1, "location": 1})
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("name_location"))
+                    .build(),
+            )
+            .build();
+        let last_seen = IndexModel::builder()
+            .keys(doc! {"last_seen": -1})
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("last_seen"))
+                    .build(),
+            )
+            .build();
+        let email = IndexModel::builder()
+            .keys(doc! {"email": 1})
+            .options(
+                IndexOptions::builder()
+                    .name(String::from(EMAIL_INDEX))
+                    .unique(true)
+                    .partial_filter_expression(doc! {"email": {"$gt": ""}})
+                    .build(),
+            )
+            .build();
+        let updated_at = IndexModel::builder()
+            .keys(doc! {"updated_at": -1})
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("updated_at"))
+                    .build(),
+            )
+            .build();
+        self.col()
+            .create_indexes(vec![name_location, last_seen, email, updated_at], None)
+            .await?;
+        let scroll_expiry = IndexModel::builder()
+            .keys(doc! {"expires_at": 1})
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("expires_at"))
+                    .expire_after(Duration::ZERO)
+                    .build(),
+            )
+            .build();
+        self.scroll_col().create_index(scroll_expiry, None).await?;
+        Ok(())
+    }
+
+    /// Creates a new user in the database asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_user` - The user object to be created.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the stored `User`, with its `id`, `member_number` and timestamps
+    /// filled in, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::DuplicateEmail` if another user already has the same email, or
+    /// `RepoError::Database` if there is an issue with creating the user in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use crate::models::User;
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
     /// let new_user = User {
     ///     id: None,
     ///     name: String::from("John Doe"),
     ///     location: String::from("New York"),
     ///     title: String::from("Software Engineer"),
     /// };
-    /// let result = repo.create_user(new_user).await?;
-    /// println!("User created successfully: {:?}", result);
+    /// let user = repo.create_user(new_user).await?;
+    /// println!("User created with id {:?}", user.id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_user(&self, new_user: User) -> Result<User, RepoError> {
+        let member_number = self.next_sequence(&self.collection_name).await?;
+        let now = DateTime::now();
+        let new_user = User {
+            member_number: Some(member_number),
+            created_at: Some(now),
+            updated_at: Some(now),
+            ..new_user
+        };
+        let result = self
+            .create(new_user.clone())
+            .await
+            .map_err(email_conflict)?;
+        Ok(User {
+            id: result.inserted_id.as_object_id(),
+            ..new_user
+        })
+    }
+
+    /// Creates several users in a single round trip asynchronously.
+    ///
+    /// The insert is unordered, so a user that fails, for example on a taken email, does not
+    /// stop the others from being created.
+    ///
+    /// # Arguments
+    ///
+    /// * `users` - The users to create. Their `id` is replaced with a freshly generated one.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing one entry per user, in the order of `users`: the ID of the created
+    /// user, or the `RepoError` it failed with (`RepoError::DuplicateEmail` for a taken email).
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::EmptyBatch` without querying the database if `users` is empty, or
+    /// `RepoError::Database` if the batch as a whole could not be inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use crate::models::User;
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository, users: Vec<User>) -> Result<(), RepoError> {
+    /// let results = repo.create_many_users(users).await?;
+    /// println!("Created {} users", results.iter().filter(|result| result.is_ok()).count());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_many_users(
+        &self,
+        users: Vec<User>,
+    ) -> Result<Vec<Result<ObjectId, RepoError>>, RepoError> {
+        if users.is_empty() {
+            return Err(RepoError::EmptyBatch);
+        }
+        let last = self
+            .reserve_sequence(&self.collection_name, users.len() as i64)
+            .await?;
+        let first = last - users.len() as i64 + 1;
+        let now = DateTime::now();
+        let ids = users.iter().map(|_| ObjectId::new()).collect::<Vec<_>>();
+        let users = users.into_iter().zip(ids.iter().copied()).zip(first..).map(
+            |((user, id), member_number)| User {
+                id: Some(id),
+                member_number: Some(member_number),
+                created_at: Some(now),
+                updated_at: Some(now),
+                ..user
+            },
+        );
+        let col = self.col();
+        let options = InsertManyOptions::builder().ordered(false).build();
+        let insert = col.insert_many(users, options);
+        let err = match self.track(Operation::Insert, insert).await {
+            Ok(_) => return Ok(ids.into_iter().map(Ok).collect()),
+            Err(err) => err,
+        };
+        let write_errors = match err.kind.as_ref() {
+            ErrorKind::BulkWrite(failure) => failure.write_errors.clone(),
+            _ => None,
+        };
+        let Some(write_errors) = write_errors else {
+            return Err(email_conflict(err));
+        };
+        let results = ids
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| {
+                match write_errors
+                    .iter()
+                    .find(|write_error| write_error.index == index)
+                {
+                    None => Ok(id),
+                    Some(write_error)
+                        if violates_email_index(write_error.code, &write_error.message) =>
+                    {
+                        Err(RepoError::DuplicateEmail)
+                    }
+                    Some(_) => Err(RepoError::Database(err.clone())),
+                }
+            })
+            .collect();
+        Ok(results)
+    }
+
+    /// Allocates the next number of a named sequence asynchronously.
+    ///
+    /// Sequences live in the `counters` collection and start at 1. Allocation is a single atomic
+    /// `$inc`, so concurrent callers never receive the same number.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the sequence.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the allocated number, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if there is an issue with updating the counter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let number = repo.next_sequence("invoices").await?;
+    /// println!("Allocated invoice number {}", number);
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn create_user(&self, new_user: User) -> mongodb::error::Result<InsertOneResult> {
-        self.col.insert_one(new_user, None).await
+    pub async fn next_sequence(&self, name: &str) -> Result<i64, RepoError> {
+        self.reserve_sequence(name, 1).await
+    }
+
+    /// Atomically advances a named sequence by `count` and returns the last number reserved.
+    async fn reserve_sequence(&self, name: &str, count: i64) -> Result<i64, RepoError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let col = self.counters_col();
+        let update =
+            col.find_one_and_update(doc! {"_id": name}, doc! {"$inc": {"seq": count}}, options);
+        let counter = self.track(Operation::Update, update).await?;
+        let seq = counter
+            .as_ref()
+            .and_then(|counter| counter.get_i64("seq").ok());
+        seq.ok_or_else(|| mongodb::error::Error::custom("sequence counter was not returned").into())
     }
 
     /// Retrieves a user from the database asynchronously.
@@ -87,14 +795,16 @@ impl MongoRepo {
     /// # Arguments
     ///
     /// * `id` - A reference to a string representing the ID of the user to retrieve.
+    /// * `read_concern` - The read concern to use instead of the collection default, if any.
     ///
     /// # Returns
     ///
-    /// A `Result` containing the retrieved `User` object if successful, or an `Error` if an error occurs.
+    /// A `Result` containing the retrieved `User` object, `None` if no user has that ID, or an `Error` if an error occurs.
     ///
     /// # Errors
     ///
-    /// This function may return an error if there is an issue with retrieving the user from the database.
+    /// Returns `RepoError::InvalidObjectId` if `id` is not a valid ObjectId, or `RepoError::Database`
+    /// if there is an issue with retrieving the user from the database.
     ///
     /// # Examples
     ///
@@ -104,26 +814,36 @@ impl MongoRepo {
     /// # use your_project_name::repository::YourRepository;
     /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
     /// let id = String::from("some_id");
-    /// let user = repo.get_user(&id).await?;
+    /// let user = repo.get_user(&id, None).await?;
     /// println!("User details: {:?}", user);
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn get_user(&self, id: &String) -> Result<User, Error> {
-        let obj_id = ObjectId::parse_str(id).unwrap();
+    pub async fn get_user(
+        &self,
+        id: &str,
+        read_concern: Option<ReadConcern>,
+    ) -> Result<Option<User>, RepoError> {
+        let obj_id = parse_object_id(id)?;
         let filter = doc! {"_id": obj_id};
-        let user_detail = self
-            .col
-            .find_one(filter, None)
-            .await
-            .ok()
-            .expect("Error getting user's detail");
+        let mut options = FindOneOptions::default();
+        options.read_concern = read_concern;
+        let col = self.col();
+        let user_detail = retry_read(read_retry_attempts(), || {
+            self.track(
+                Operation::Find,
+                col.find_one(filter.clone(), options.clone()),
+            )
+        })
+        .await?;
 
-        Ok(user_detail.unwrap())
+        Ok(user_detail)
     }
 
     /// Updates a user in the database asynchronously.
     ///
+    /// An empty `email` leaves the stored email untouched, so clients that omit it do not wipe it.
+    ///
     /// # Arguments
     ///
     /// * `id` - A reference to a string representing the ID of the user to update.
@@ -135,7 +855,9 @@ impl MongoRepo {
     ///
     /// # Errors
     ///
-    /// This function may return an error if there is an issue with updating the user in the database.
+    /// Returns `RepoError::InvalidObjectId` if `id` is not a valid ObjectId, `RepoError::NotFound` if
+    /// no user has that ID, `RepoError::DuplicateEmail` if another user already has the new email,
+    /// or `RepoError::Database` if there is an issue with updating the user in the database.
     ///
     /// # Examples
     ///
@@ -157,69 +879,137 @@ impl MongoRepo {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn update_user(&self, id: &String, new_user: User) -> Result<UpdateResult, Error> {
-        let obj_id = ObjectId::parse_str(id).unwrap();
+    pub async fn update_user(&self, id: &str, new_user: User) -> Result<UpdateResult, RepoError> {
+        let obj_id = parse_object_id(id)?;
         let filter = doc! {"_id": obj_id};
-        let new_doc = doc! {
-            "$set":
-                {
-                    "id": new_user.id,
-                    "name": new_user.name,
-                    "location": new_user.location,
-                    "title": new_user.title
-                },
+        let mut fields = doc! {
+            "name": new_user.name,
+            "location": new_user.location,
+            "title": new_user.title,
+            "updated_at": DateTime::now()
         };
+        if !new_user.email.is_empty() {
+            fields.insert("email", new_user.email);
+        }
+        let new_doc = doc! {"$set": fields};
         let updated_doc = self
-            .col
-            .update_one(filter, new_doc, None)
+            .track(
+                Operation::Update,
+                self.col().update_one(filter, new_doc, None),
+            )
             .await
-            .ok()
-            .expect("Error updating user");
+            .map_err(email_conflict)?;
+        if updated_doc.matched_count == 0 {
+            return Err(RepoError::NotFound);
+        }
         Ok(updated_doc)
     }
 
-    /// Deletes a user from the database asynchronously.
+    /// Inserts a user, or updates the user with the same email, asynchronously.
+    ///
+    /// An existing user gets the new name, location and title. A new user is stamped like one
+    /// created through `create_user`, with a `created_at` and a `member_number`. The number is
+    /// only drawn from the sequence once the upsert has actually inserted, so updates never
+    /// leave gaps, and it is only set on a user that has none yet.
     ///
     /// # Arguments
     ///
-    /// * `id` - A reference to a string representing the ID of the user to delete.
+    /// * `user` - The user to store, keyed by its `email`.
     ///
     /// # Returns
     ///
-    /// A `Result` containing a `DeleteResult` if successful, or an `Error` if an error occurs.
+    /// A `Result` containing an `UpdateResult` whose `upserted_id` is set when a new user was
+    /// created, or a `RepoError` if an error occurs.
     ///
     /// # Errors
     ///
-    /// This function may return an error if there is an issue with deleting the user from the database.
+    /// Returns `RepoError::EmptyEmail` without querying the database if the email is empty, or
+    /// `RepoError::Database` if there is an issue with writing the user.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// # use mongodb::error::Error;
-    /// # use mongodb::results::DeleteResult;
-    /// # use your_project_name::repository::YourRepository;
-    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
-    /// let id = String::from("some_id");
-    /// let result = repo.delete_user(&id).await?;
-    /// println!("User deleted successfully: {:?}", result);
+    /// # use crate::models::User;
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository, user: User) -> Result<(), RepoError> {
+    /// let result = repo.upsert_user_by_email(&user).await?;
+    /// println!("Created: {}", result.upserted_id.is_some());
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn delete_user(&self, id: &String) -> Result<DeleteResult, Error> {
-        let obj_id = ObjectId::parse_str(id).unwrap();
-        let filter = doc! {"_id": obj_id};
-        let user_detail = self
-            .col
-            .delete_one(filter, None)
-            .await
-            .ok()
-            .expect("Error deleting user");
-
-        Ok(user_detail)
+    pub async fn upsert_user_by_email(&self, user: &User) -> Result<UpdateResult, RepoError> {
+        if user.email.is_empty() {
+            return Err(RepoError::EmptyEmail);
+        }
+        let now = DateTime::now();
+        let filter = doc! {"email": &user.email};
+        let update = doc! {
+            "$set": {
+                "name": &user.name,
+                "location": &user.location,
+                "title": &user.title,
+                "updated_at": now,
+            },
+            "$setOnInsert": {"created_at": now},
+        };
+        let options = UpdateOptions::builder().upsert(true).build();
+        let result = self
+            .track(
+                Operation::Update,
+                self.col().update_one(filter, update, options),
+            )
+            .await?;
+        if let Some(id) = &result.upserted_id {
+            let member_number = self.next_sequence(&self.collection_name).await?;
+            let unnumbered = doc! {"_id": id, "member_number": {"$exists": false}};
+            let stamp = doc! {"$set": {"member_number": member_number}};
+            self.track(
+                Operation::Update,
+                self.col().update_one(unnumbered, stamp, None),
+            )
+            .await?;
+        }
+        Ok(result)
+    }
+
+    /// Deletes a user from the database asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A reference to a string representing the ID of the user to delete.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `DeleteResult` if successful, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::InvalidObjectId` if `id` is not a valid ObjectId, `RepoError::NotFound` if
+    /// no user has that ID, or `RepoError::Database` if there is an issue with deleting the user from the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use mongodb::results::DeleteResult;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let id = String::from("some_id");
+    /// let result = repo.delete_user(&id).await?;
+    /// println!("User deleted successfully: {:?}", result);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_user(&self, id: &str) -> Result<DeleteResult, RepoError> {
+        self.delete(id).await
     }
 
     /// Retrieves all users from the database asynchronously.
     ///
+    /// # Arguments
+    ///
+    /// * `read_concern` - The read concern to use instead of the collection default, if any.
+    ///
     /// # Returns
     ///
     /// A `Result` containing a vector of `User` objects if successful, or an `Error` if an error occurs.
@@ -235,102 +1025,3103 @@ impl MongoRepo {
     /// # use mongodb::error::Error;
     /// # use your_project_name::repository::YourRepository;
     /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
-    /// let users = repo.get_all_users().await?;
+    /// let users = repo.get_all_users(None).await?;
     /// for user in users {
     ///     println!("User ID: {}, Name: {}", user.id, user.name);
     /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn get_all_users(&self) -> Result<Vec<User>, Error> {
-        let mut cursors = self
-            .col
-            .find(None, None)
-            .await
-            .ok()
-            .expect("Error getting list of users");
+    pub async fn get_all_users(
+        &self,
+        read_concern: Option<ReadConcern>,
+    ) -> Result<Vec<User>, RepoError> {
+        self.get_users_paginated(0, 0, read_concern).await
+    }
+
+    /// Retrieves one page of users from the database asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `skip` - The number of users to skip before the page starts.
+    /// * `limit` - The maximum number of users to return. Zero or a negative value means no limit.
+    /// * `read_concern` - The read concern to use instead of the collection default, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a vector of `User` objects if successful, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database or mapping through the cursor.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let users = repo.get_users_paginated(20, 10, None).await?;
+    /// println!("Users: {:?}", users);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_users_paginated(
+        &self,
+        skip: u64,
+        limit: i64,
+        read_concern: Option<ReadConcern>,
+    ) -> Result<Vec<User>, RepoError> {
+        let options = page_options(skip, limit, read_concern);
+        let col = &self.col();
+        let options = &options;
+        let metrics = &self.metrics;
+        retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(None, options.clone()))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Retrieves a random sample of users from the database asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of users to return.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing at most `n` distinct `User` objects picked at random, or an `Error` if an error occurs.
+    /// When the collection holds fewer than `n` users, all of them are returned.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the `$sample` aggregation fails or a document cannot be deserialized.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let users = repo.sample_users(50).await?;
+    /// println!("Sampled {} users", users.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sample_users(&self, n: i64) -> Result<Vec<User>, RepoError> {
+        let pipeline = &vec![doc! { "$sample": { "size": n } }];
+        let col = &self.col();
+        let documents: Vec<Document> = retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Aggregate, col.aggregate(pipeline.clone(), None))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await?;
         let mut users: Vec<User> = Vec::new();
-        while let Some(user) = cursors
-            .try_next()
-            .await
-            .ok()
-            .expect("Error mapping through cursor")
-        {
-            users.push(user)
+        for document in documents {
+            users.push(from_document(document)?);
         }
         Ok(users)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mongodb::bson::oid::ObjectId;
+    /// Retrieves the preferences subdocument of a user asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the user whose preferences should be read.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `None` if no user matches `id`, otherwise the user's preferences
+    /// (an empty document when none were stored), or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// if let Some(preferences) = repo.get_preferences(&id).await? {
+    ///     println!("Preferences: {}", preferences);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_preferences(&self, id: &ObjectId) -> Result<Option<Document>, RepoError> {
+        let filter = doc! {"_id": id};
+        let options = FindOneOptions::builder()
+            .projection(doc! {"preferences": 1})
+            .build();
+        let col = self.col().clone_with_type::<Document>();
+        let user_doc = retry_read(read_retry_attempts(), || {
+            self.track(
+                Operation::Find,
+                col.find_one(filter.clone(), options.clone()),
+            )
+        })
+        .await?;
 
-    #[tokio::test]
-    async fn test_create_user() {
-        // Arrange
-        let repo = MongoRepo::init().await;
-        let new_user = User {
-            id: Some(ObjectId::new()),
-            name: String::from("Test User"),
-            location: String::from("Test Location"),
-            title: String::from("Test Title"),
-        };
+        Ok(user_doc.map(|user_doc| {
+            user_doc
+                .get_document("preferences")
+                .cloned()
+                .unwrap_or_default()
+        }))
+    }
 
-        // Act
-        let result = repo.create_user(new_user.clone()).await;
+    /// Replaces the preferences subdocument of a user asynchronously and bumps its `updated_at`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the user whose preferences should be replaced.
+    /// * `preferences` - The new preferences subdocument.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an `UpdateResult` if successful, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with updating the user in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::{doc, oid::ObjectId};
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// let result = repo.set_preferences(&id, doc! {"theme": "dark"}).await?;
+    /// println!("Preferences updated: {:?}", result);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_preferences(
+        &self,
+        id: &ObjectId,
+        preferences: Document,
+    ) -> Result<UpdateResult, RepoError> {
+        let filter = doc! {"_id": id};
+        let update = doc! {"$set": {"preferences": preferences, "updated_at": DateTime::now()}};
+        self.track(
+            Operation::Update,
+            self.col().update_one(filter, update, None),
+        )
+        .await
+        .map_err(RepoError::from)
+    }
 
-        // Assert
-        assert!(result.is_ok(), "Failed to create user: {:?}", result.err());
-        let inserted_user = result.unwrap();
-        assert_ne!(inserted_user.inserted_id, new_user.id.into());
+    /// Retrieves a user by its exact name and location asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the user to retrieve.
+    /// * `location` - The location of the user to retrieve.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the matching `User`, `None` if no user matches both fields, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// if let Some(user) = repo.get_by_name_and_location("John Doe", "New York").await? {
+    ///     println!("User details: {:?}", user);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_by_name_and_location(
+        &self,
+        name: &str,
+        location: &str,
+    ) -> Result<Option<User>, RepoError> {
+        let filter = doc! {"name": name, "location": location};
+        let col = self.col();
+        retry_read(read_retry_attempts(), || {
+            self.track(Operation::Find, col.find_one(filter.clone(), None))
+        })
+        .await
+        .map_err(RepoError::from)
     }
 
-    #[tokio::test]
-    async fn test_get_user() {
-        // Arrange
-        let repo = MongoRepo::init().await;
-        let id = Some(mongodb::bson::oid::ObjectId::new()); // Generate a new ObjectId
+    /// Retrieves a user by email asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - The email of the user to retrieve.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the matching `User`, `None` if no user has that email, or a
+    /// `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::EmptyEmail` without querying the database if `email` is empty, or
+    /// `RepoError::Database` if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// if let Some(user) = repo.get_user_by_email("john@example.com").await? {
+    ///     println!("User details: {:?}", user);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
+        if email.is_empty() {
+            return Err(RepoError::EmptyEmail);
+        }
+        let filter = doc! {"email": email};
+        let col = self.col();
+        retry_read(read_retry_attempts(), || {
+            self.track(Operation::Find, col.find_one(filter.clone(), None))
+        })
+        .await
+        .map_err(RepoError::from)
+    }
 
-        // Create a user before trying to retrieve it
-        let new_user = User {
-            id: id.clone(),
-            name: "Expected Name".to_string(),
-            location: "Some Location".to_string(), // Add a location
-            title: "Some Title".to_string(), // Add a title
-        };
-        let create_result = repo.create_user(new_user).await;
-        assert!(create_result.is_ok(), "Failed to create user: {:?}", create_result.err());
+    /// Retrieves the distinct locations and titles of all users in a single aggregation.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `FilterOptions` with both lists deduplicated and sorted, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the `$facet` aggregation fails or its result cannot be deserialized.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let options = repo.filter_options().await?;
+    /// println!("Locations: {:?}, titles: {:?}", options.locations, options.titles);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn filter_options(&self) -> Result<FilterOptions, RepoError> {
+        let pipeline = vec![
+            doc! {
+                "$facet": {
+                    "locations": [
+                        { "$group": { "_id": "$location" } },
+                        { "$sort": { "_id": 1 } },
+                    ],
+                    "titles": [
+                        { "$group": { "_id": "$title" } },
+                        { "$sort": { "_id": 1 } },
+                    ],
+                }
+            },
+            doc! {
+                "$project": {
+                    "locations": "$locations._id",
+                    "titles": "$titles._id",
+                }
+            },
+        ];
+        let pipeline = &pipeline;
+        let col = &self.col();
+        let document = retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Aggregate, col.aggregate(pipeline.clone(), None))
+                .await?
+                .try_next()
+                .await
+        })
+        .await?;
+        match document {
+            Some(document) => Ok(from_document(document)?),
+            None => Ok(FilterOptions::default()),
+        }
+    }
 
-        // Act
-        let result = match repo.get_user(&id.unwrap().to_string()).await {
-            Ok(user) => user,
-            Err(e) => panic!("Failed to get user: {:?}", e),
-        };
+    /// Deletes every user whose ID is in `ids` asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - The IDs of the users to delete.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the IDs this call actually deleted, or an `Error` if an error occurs.
+    /// Each ID is deleted on its own, so IDs without a matching user, including users removed by
+    /// a concurrent request, are simply absent from the returned vector.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with deleting users in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, ids: Vec<ObjectId>) -> Result<(), Error> {
+    /// let deleted = repo.delete_users_by_ids(&ids).await?;
+    /// println!("Deleted {} users", deleted.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_users_by_ids(&self, ids: &[ObjectId]) -> Result<Vec<ObjectId>, RepoError> {
+        let col = self.col();
+        let mut deleted: Vec<ObjectId> = Vec::new();
+        for id in ids {
+            let delete = col.delete_one(doc! {"_id": id}, None);
+            if self.track(Operation::Delete, delete).await?.deleted_count == 1 {
+                deleted.push(*id);
+            }
+        }
+        Ok(deleted)
+    }
 
-        // Assert
-        assert_eq!(result.name, "Expected Name", "User name does not match");
+    /// Exports every user document as a native BSON dump asynchronously.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the documents serialized back to back as BSON, or an `Error` if an error occurs.
+    /// Each BSON document starts with its own length, so the dump needs no extra framing.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database or serializing a document.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let dump = repo.dump().await?;
+    /// println!("Dump is {} bytes long", dump.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn dump(&self) -> Result<Vec<u8>, RepoError> {
+        let col = &self.col().clone_with_type::<Document>();
+        let mut options = FindOptions::default();
+        options.batch_size = batch_size();
+        let options = &options;
+        let documents: Vec<Document> = retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Find, col.find(None, options.clone()))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await?;
+        let mut bytes: Vec<u8> = Vec::new();
+        for document in documents {
+            document.to_writer(&mut bytes)?;
+        }
+        Ok(bytes)
     }
 
-    #[tokio::test]
-    async fn test_update_user() {
-        // Arrange
-        let repo = MongoRepo::init().await;
-        let id = String::from("some_id"); // Provide an existing user ID
-        let updated_user = User {
-            id: Some(ObjectId::new()), // Provide a new ID or the same ID
-            name: String::from("Updated Name"),
-            location: String::from("Updated Location"),
+    /// Restores user documents from a BSON dump produced by `dump` asynchronously.
+    ///
+    /// Documents whose `_id` already exists in the collection are left untouched, so restoring
+    /// the same dump twice is harmless.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The BSON dump to restore.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of documents inserted, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the dump is malformed or a document cannot be inserted
+    /// for a reason other than a duplicate `_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, dump: Vec<u8>) -> Result<(), Error> {
+    /// let restored = repo.restore(&dump).await?;
+    /// println!("Restored {} users", restored);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn restore(&self, bytes: &[u8]) -> Result<usize, RepoError> {
+        let mut reader = Cursor::new(bytes);
+        let mut documents: Vec<Document> = Vec::new();
+        while (reader.position() as usize) < bytes.len() {
+            documents.push(Document::from_reader(&mut reader)?);
+        }
+        if documents.is_empty() {
+            return Ok(0);
+        }
+
+        let total = documents.len();
+        let options = InsertManyOptions::builder().ordered(false).build();
+        let col = self.col().clone_with_type::<Document>();
+        let insert = col.insert_many(documents, options);
+        let result = self.track(Operation::Insert, insert).await;
+        match result {
+            Ok(inserted) => Ok(inserted.inserted_ids.len()),
+            Err(err) => match err.kind.as_ref() {
+                ErrorKind::BulkWrite(failure) if failure.write_concern_error.is_none() => {
+                    let write_errors = failure.write_errors.as_deref().unwrap_or_default();
+                    if write_errors
+                        .iter()
+                        .all(|write_error| write_error.code == 11000)
+                    {
+                        Ok(total - write_errors.len())
+                    } else {
+                        Err(err.into())
+                    }
+                }
+                _ => Err(err.into()),
+            },
+        }
+    }
+
+    /// Computes how many users hold each title within each location asynchronously.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing one `LocationTitleCounts` per location, sorted by location, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the aggregation fails or its result cannot be deserialized.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// for location in repo.title_distribution_by_location().await? {
+    ///     println!("{}: {} users", location.location, location.total);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn title_distribution_by_location(
+        &self,
+    ) -> Result<Vec<LocationTitleCounts>, RepoError> {
+        let pipeline = vec![
+            doc! {
+                "$group": {
+                    "_id": { "location": "$location", "title": "$title" },
+                    "count": { "$sum": 1 },
+                }
+            },
+            doc! { "$sort": { "_id.title": 1 } },
+            doc! {
+                "$group": {
+                    "_id": "$_id.location",
+                    "total": { "$sum": "$count" },
+                    "titles": { "$push": { "title": "$_id.title", "count": "$count" } },
+                }
+            },
+            doc! { "$sort": { "_id": 1 } },
+            doc! {
+                "$project": { "_id": 0, "location": "$_id", "total": 1, "titles": 1 }
+            },
+        ];
+        let pipeline = &pipeline;
+        let col = &self.col();
+        let documents: Vec<Document> = retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Aggregate, col.aggregate(pipeline.clone(), None))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await?;
+        let mut distribution: Vec<LocationTitleCounts> = Vec::new();
+        for document in documents {
+            distribution.push(from_document(document)?);
+        }
+        Ok(distribution)
+    }
+
+    /// Marks a user as seen right now asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the user to touch.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an `UpdateResult` if successful, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with updating the user in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// let result = repo.touch_user(&id).await?;
+    /// println!("User touched: {:?}", result);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn touch_user(&self, id: &ObjectId) -> Result<UpdateResult, RepoError> {
+        let filter = doc! {"_id": id};
+        let update = doc! {"$set": {"last_seen": DateTime::now()}};
+        self.track(
+            Operation::Update,
+            self.col().update_one(filter, update, None),
+        )
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Retrieves the users seen at or after `ts` asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `ts` - The earliest `last_seen` time to include.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the active `User` objects, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database or mapping through the cursor.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::DateTime;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let fifteen_minutes_ago = DateTime::from_millis(DateTime::now().timestamp_millis() - 15 * 60 * 1000);
+    /// let users = repo.active_since(fifteen_minutes_ago).await?;
+    /// println!("{} users online", users.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn active_since(&self, ts: DateTime) -> Result<Vec<User>, RepoError> {
+        let filter = &doc! {"last_seen": {"$gte": ts}};
+        let col = &self.col();
+        retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Find, col.find(filter.clone(), None))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Claims a user for exclusive processing by `worker` asynchronously.
+    ///
+    /// The claim only succeeds when the user is unclaimed or its previous claim has expired, and
+    /// is taken atomically with `find_one_and_update`, so two workers can never hold it at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the user to claim.
+    /// * `worker` - An identifier of the worker taking the claim.
+    /// * `ttl` - How long the claim lasts unless released earlier.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the claimed `User`, `None` if the user does not exist or is claimed by someone else,
+    /// or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with updating the user in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// if let Some(user) = repo.claim_user(&id, "worker-1", Duration::from_secs(60)).await? {
+    ///     println!("Processing {}", user.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn claim_user(
+        &self,
+        id: &ObjectId,
+        worker: &str,
+        ttl: Duration,
+    ) -> Result<Option<User>, RepoError> {
+        let now = DateTime::now();
+        let ttl_millis = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+        let locked_until = DateTime::from_millis(now.timestamp_millis().saturating_add(ttl_millis));
+        let filter = doc! {
+            "_id": id,
+            "$or": [
+                { "locked_until": null },
+                { "locked_until": { "$lte": now } },
+            ],
+        };
+        let update = doc! {"$set": {"locked_by": worker, "locked_until": locked_until}};
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let col = self.col();
+        let claim = col.find_one_and_update(filter, update, options);
+        self.track(Operation::Update, claim)
+            .await
+            .map_err(RepoError::from)
+    }
+
+    /// Releases the claim `worker` holds on a user asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the claimed user.
+    /// * `worker` - The worker that holds the claim.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an `UpdateResult` whose `matched_count` is 0 when `worker` held no claim on the user,
+    /// or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with updating the user in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// let result = repo.release_user(&id, "worker-1").await?;
+    /// println!("Claim released: {:?}", result);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn release_user(
+        &self,
+        id: &ObjectId,
+        worker: &str,
+    ) -> Result<UpdateResult, RepoError> {
+        let filter = doc! {"_id": id, "locked_by": worker};
+        let update = doc! {"$unset": {"locked_by": "", "locked_until": ""}};
+        self.track(
+            Operation::Update,
+            self.col().update_one(filter, update, None),
+        )
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Returns the next batch of a resumable scroll over all users asynchronously.
+    ///
+    /// The scroll position is persisted in the `Scroll` collection, so a client can resume it with
+    /// the returned scroll ID after a restart. A scroll expires when it is not resumed for 15 minutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `scroll_id` - The ID of the scroll to resume, or `None` to start a new one.
+    /// * `size` - The maximum number of users in the batch.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the next `ScrollPage`, `None` if `scroll_id` is unknown or expired,
+    /// or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database or saving the scroll position.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let mut page = repo.scroll_users(None, 500).await?.unwrap();
+    /// while !page.done {
+    ///     let scroll_id = page.scroll_id.parse().unwrap();
+    ///     page = repo.scroll_users(Some(scroll_id), 500).await?.unwrap();
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn scroll_users(
+        &self,
+        scroll_id: Option<ObjectId>,
+        size: i64,
+    ) -> Result<Option<ScrollPage>, RepoError> {
+        let now = DateTime::now();
+        let mut state = match scroll_id {
+            Some(scroll_id) => {
+                let filter = doc! {"_id": scroll_id, "expires_at": {"$gt": now}};
+                let scroll_col = self.scroll_col();
+                let state = retry_read(read_retry_attempts(), || {
+                    self.track(Operation::Find, scroll_col.find_one(filter.clone(), None))
+                })
+                .await?;
+                match state {
+                    Some(state) => state,
+                    None => return Ok(None),
+                }
+            }
+            None => ScrollState {
+                id: ObjectId::new(),
+                last_id: None,
+                expires_at: now,
+            },
+        };
+
+        let filter = state.last_id.map(|last_id| doc! {"_id": {"$gt": last_id}});
+        let mut options = FindOptions::builder()
+            .sort(doc! {"_id": 1})
+            .limit(size)
+            .build();
+        options.batch_size = batch_size();
+        let (filter, options) = (&filter, &options);
+        let col = &self.col();
+        let users: Vec<User> = retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Find, col.find(filter.clone(), options.clone()))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await?;
+
+        if let Some(last_id) = users.last().and_then(|user| user.id) {
+            state.last_id = Some(last_id);
+        }
+        state.expires_at =
+            DateTime::from_millis(now.timestamp_millis() + SCROLL_TTL.as_millis() as i64);
+        let options = ReplaceOptions::builder().upsert(true).build();
+        let scroll_col = self.scroll_col();
+        let replace = scroll_col.replace_one(doc! {"_id": state.id}, &state, options);
+        self.track(Operation::Update, replace).await?;
+
+        Ok(Some(ScrollPage {
+            scroll_id: state.id.to_hex(),
+            done: (users.len() as i64) < size,
+            users,
+        }))
+    }
+
+    /// Retrieves all users with a projection applied asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `projection` - The projection document passed to `find`, e.g. `{"preferences": 0}`.
+    /// * `read_concern` - The read concern to use instead of the collection default, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the projected user documents, or an `Error` if an error occurs.
+    /// Documents are returned untyped because a projection may leave out required `User` fields.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the projection is invalid or there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::doc;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let users = repo.find_users_with_projection(doc! {"name": 1}, None).await?;
+    /// println!("Users: {:?}", users);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn find_users_with_projection(
+        &self,
+        projection: Document,
+        read_concern: Option<ReadConcern>,
+    ) -> Result<Vec<Document>, RepoError> {
+        let mut options = FindOptions::builder().projection(projection).build();
+        options.read_concern = read_concern;
+        options.batch_size = batch_size();
+        let col = &self.col().clone_with_type::<Document>();
+        let options = &options;
+        let metrics = &self.metrics;
+        retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(None, options.clone()))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Retrieves only the given fields of all users asynchronously.
+    ///
+    /// `_id` is always returned alongside the requested fields. Use `find_users_with_projection`
+    /// to leave fields out instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `fields` - The fields to return, e.g. `["name"]` for an autocomplete list.
+    /// * `read_concern` - The read concern to use instead of the collection default, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the projected user documents, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if a field name is invalid or there is an issue with querying
+    /// the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let names = repo.list_users_projected(&["name"], None).await?;
+    /// println!("Names: {:?}", names);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn list_users_projected(
+        &self,
+        fields: &[&str],
+        read_concern: Option<ReadConcern>,
+    ) -> Result<Vec<Document>, RepoError> {
+        let projection = fields
+            .iter()
+            .map(|field| (field.to_string(), Bson::Int32(1)))
+            .collect::<Document>();
+        self.find_users_with_projection(projection, read_concern)
+            .await
+    }
+
+    /// Recomputes the content hash of a user asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the user to hash.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the hash, `None` if no user matches, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// let hash = repo.content_hash(&id).await?;
+    /// println!("Content hash: {:?}", hash);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn content_hash(&self, id: &ObjectId) -> Result<Option<String>, RepoError> {
+        let col = self.col();
+        let user = retry_read(read_retry_attempts(), || {
+            self.track(Operation::Find, col.find_one(doc! {"_id": id}, None))
+        })
+        .await?;
+        Ok(user.map(|user| user.content_hash()))
+    }
+
+    /// Returns a snapshot of the database operations this repository has performed.
+    ///
+    /// # Returns
+    ///
+    /// A `RepoMetricsSnapshot` with per-operation counts, the number of failed operations and
+    /// the number of operations currently in flight.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::YourRepository;
+    /// # fn example_function(repo: &YourRepository) {
+    /// let metrics = repo.metrics();
+    /// println!("{} finds so far", metrics.finds);
+    /// # }
+    /// ```
+    pub fn metrics(&self) -> RepoMetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Returns the newest `updated_at` across all users asynchronously.
+    ///
+    /// The value is cached until the next write through this repository, so repeated calls
+    /// between writes do not query the database.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the timestamp, `None` if no user has an `updated_at`, or a
+    /// `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let last_modified = repo.last_modified().await?;
+    /// println!("Last modified: {:?}", last_modified);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn last_modified(&self) -> Result<Option<DateTime>, RepoError> {
+        if let Some(last_modified) = self.last_modified_cache.get() {
+            return Ok(last_modified);
+        }
+        let generation = self.last_modified_cache.generation();
+        let options = FindOneOptions::builder()
+            .sort(doc! {"updated_at": -1})
+            .projection(doc! {"updated_at": 1})
+            .build();
+        let col = self.col().clone_with_type::<Document>();
+        let newest = retry_read(read_retry_attempts(), || {
+            self.track(Operation::Find, col.find_one(None, options.clone()))
+        })
+        .await?;
+        let last_modified = newest.and_then(|user| user.get_datetime("updated_at").ok().copied());
+        self.last_modified_cache.store(generation, last_modified);
+        Ok(last_modified)
+    }
+
+    /// Retrieves all users sorted by a single field asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `sort_field` - The field to sort by; one of `name`, `location` or `title`.
+    /// * `ascending` - Whether to sort in ascending (`true`) or descending (`false`) order.
+    /// * `read_concern` - The read concern to use instead of the collection default, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the sorted users if successful, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::InvalidSortField` without querying the database if `sort_field` is not
+    /// sortable, or `RepoError::Database` if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let users = repo.get_all_users_sorted("name", true, None).await?;
+    /// println!("Users: {:?}", users);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_all_users_sorted(
+        &self,
+        sort_field: &str,
+        ascending: bool,
+        read_concern: Option<ReadConcern>,
+    ) -> Result<Vec<User>, RepoError> {
+        if !SORTABLE_FIELDS.contains(&sort_field) {
+            return Err(RepoError::InvalidSortField(sort_field.to_owned()));
+        }
+        let mut options = FindOptions::builder()
+            .sort(doc! { sort_field: if ascending { 1 } else { -1 } })
+            .build();
+        options.read_concern = read_concern;
+        options.batch_size = batch_size();
+        let col = &self.col();
+        let options = &options;
+        let metrics = &self.metrics;
+        let users = retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(None, options.clone()))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await?;
+        Ok(users)
+    }
+
+    /// Retrieves users whose name contains `query`, ignoring case, asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The text to look for in user names. It is matched literally, so regex
+    ///   metacharacters such as `.` or `*` have no special meaning.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the matching users, which is empty when nothing matches, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let users = repo.search_users_by_name("ali").await?;
+    /// println!("Users: {:?}", users);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn search_users_by_name(&self, query: &str) -> Result<Vec<User>, RepoError> {
+        let filter = doc! {"name": {"$regex": escape_regex(query), "$options": "i"}};
+        let col = &self.col();
+        let filter = &filter;
+        let metrics = &self.metrics;
+        retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(filter.clone(), None))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Retrieves every user referred by another user asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `referrer` - The ID of the user whose referrals to list.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the referred users, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// let referrals = repo.find_referrals(&id).await?;
+    /// println!("Referred {} users", referrals.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn find_referrals(&self, referrer: &ObjectId) -> Result<Vec<User>, RepoError> {
+        let filter = doc! {"referred_by": referrer};
+        let col = &self.col();
+        let filter = &filter;
+        let metrics = &self.metrics;
+        retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(filter.clone(), None))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Retrieves every user in a location asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - The exact location to match.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the users in `location` if successful, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::EmptyLocation` without querying the database if `location` is empty,
+    /// or `RepoError::Database` if there is an issue with querying the database or mapping through the cursor.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let users = repo.get_users_by_location("New York").await?;
+    /// println!("{} users in New York", users.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_users_by_location(&self, location: &str) -> Result<Vec<User>, RepoError> {
+        if location.is_empty() {
+            return Err(RepoError::EmptyLocation);
+        }
+        let filter = &doc! {"location": location};
+        let col = &self.col();
+        retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Find, col.find(filter.clone(), None))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Counts users, optionally only those in one location, asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - When `Some`, only users in this exact location are counted.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of matching users, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), Error> {
+    /// let total = repo.count_users(None).await?;
+    /// let in_new_york = repo.count_users(Some("New York")).await?;
+    /// println!("{} of {} users are in New York", in_new_york, total);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn count_users(&self, location: Option<&str>) -> Result<u64, RepoError> {
+        let filter = location.map(|location| doc! {"location": location});
+        let col = self.col();
+        retry_read(read_retry_attempts(), || {
+            self.track(Operation::Find, col.count_documents(filter.clone(), None))
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Retrieves a user together with other users in the same location asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the user to fetch.
+    /// * `limit` - The maximum number of colleagues to return.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the user and up to `limit` other users sharing its location,
+    /// `None` if no user matches, or an `Error` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::bson::oid::ObjectId;
+    /// # use mongodb::error::Error;
+    /// # use your_project_name::repository::YourRepository;
+    /// # async fn example_function(repo: &YourRepository, id: ObjectId) -> Result<(), Error> {
+    /// if let Some((user, colleagues)) = repo.get_with_colleagues(&id, 5).await? {
+    ///     println!("{} has {} colleagues nearby", user.name, colleagues.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_with_colleagues(
+        &self,
+        id: &ObjectId,
+        limit: i64,
+    ) -> Result<Option<(User, Vec<User>)>, RepoError> {
+        let col = &self.col();
+        let metrics = &self.metrics;
+        let user = retry_read(read_retry_attempts(), || {
+            metrics.track(Operation::Find, col.find_one(doc! {"_id": id}, None))
+        })
+        .await?;
+        let Some(user) = user else {
+            return Ok(None);
+        };
+
+        let filter = &doc! {"location": &user.location, "_id": {"$ne": id}};
+        let options = &FindOptions::builder().limit(limit).build();
+        let colleagues = retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(filter.clone(), options.clone()))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await?;
+        Ok(Some((user, colleagues)))
+    }
+
+    /// Updates only the given fields of a user asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the user to update.
+    /// * `fields` - The fields to change; fields that are `None` are left untouched.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the user after the update if successful, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::InvalidObjectId` if `id` is not a valid ObjectId, `RepoError::EmptyPatch`
+    /// if no field is set, `RepoError::NotFound` if no user has that ID, or `RepoError::Database`
+    /// if there is an issue with updating the user in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::models::UserPatch;
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let patch = UserPatch { title: Some(String::from("Manager")), ..Default::default() };
+    /// let user = repo.patch_user("some_id", patch).await?;
+    /// println!("User patched: {:?}", user);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn patch_user(&self, id: &str, fields: UserPatch) -> Result<User, RepoError> {
+        let obj_id = parse_object_id(id)?;
+        let mut set = Document::new();
+        for (key, value) in [
+            ("name", fields.name),
+            ("location", fields.location),
+            ("title", fields.title),
+        ] {
+            if let Some(value) = value {
+                set.insert(key, value);
+            }
+        }
+        if set.is_empty() {
+            return Err(RepoError::EmptyPatch);
+        }
+        set.insert("updated_at", DateTime::now());
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let col = self.col();
+        let update = col.find_one_and_update(doc! {"_id": obj_id}, doc! {"$set": set}, options);
+        let user = self.track(Operation::Update, update).await?;
+        user.ok_or(RepoError::NotFound)
+    }
+
+    /// Deletes every user in a location asynchronously.
+    ///
+    /// The matching IDs are looked up first and each user is then deleted on its own, still
+    /// filtered by `location`, so only users this call actually removed are reported. Users added
+    /// to the location after the lookup are left in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - The exact location whose users are deleted.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the IDs of the deleted users, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::EmptyLocation` without querying the database if `location` is blank, so
+    /// an empty filter can never delete the whole collection, or `RepoError::Database` if there is
+    /// an issue with deleting the users.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let deleted = repo.delete_users_by_location("Throwaway").await?;
+    /// println!("Deleted {} users", deleted.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_users_by_location(
+        &self,
+        location: &str,
+    ) -> Result<Vec<ObjectId>, RepoError> {
+        if location.trim().is_empty() {
+            return Err(RepoError::EmptyLocation);
+        }
+        let filter = &doc! {"location": location};
+        let options = &FindOptions::builder().projection(doc! {"_id": 1}).build();
+        let ids_col = &self.col().clone_with_type::<Document>();
+        let documents: Vec<Document> = retry_read(read_retry_attempts(), || async move {
+            self.track(
+                Operation::Find,
+                ids_col.find(filter.clone(), options.clone()),
+            )
+            .await?
+            .try_collect()
+            .await
+        })
+        .await?;
+        let col = self.col();
+        let mut deleted: Vec<ObjectId> = Vec::new();
+        for id in documents
+            .iter()
+            .filter_map(|document| document.get_object_id("_id").ok())
+        {
+            let delete = col.delete_one(doc! {"_id": id, "location": location}, None);
+            if self.track(Operation::Delete, delete).await?.deleted_count == 1 {
+                deleted.push(id);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Retrieves a page of matching users and the total number of matches in one round trip.
+    ///
+    /// Both come from a single `$facet` aggregation, so the count is consistent with the page.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filter users must match.
+    /// * `options` - The `sort`, `skip` and `limit` to apply to the page; other options are ignored.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the users on the page and the number of users matching `filter`,
+    /// or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if the aggregation fails or its result cannot be deserialized.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mongodb::{bson::doc, options::FindOptions};
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let options = FindOptions::builder().limit(20).build();
+    /// let (users, total) = repo.find_with_count(doc! {"location": "Berlin"}, options).await?;
+    /// println!("Showing {} of {}", users.len(), total);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn find_with_count(
+        &self,
+        filter: Document,
+        options: FindOptions,
+    ) -> Result<(Vec<User>, u64), RepoError> {
+        let mut page_stages = Vec::new();
+        if let Some(sort) = options.sort {
+            page_stages.push(doc! { "$sort": sort });
+        }
+        if let Some(skip) = options.skip {
+            // Callers keep the skip within i64; anything larger would only yield an empty page.
+            page_stages.push(doc! { "$skip": i64::try_from(skip).unwrap_or(i64::MAX) });
+        }
+        if let Some(limit) = options.limit {
+            page_stages.push(doc! { "$limit": limit });
+        }
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! {
+                "$facet": {
+                    "users": page_stages,
+                    "total": [{ "$count": "count" }],
+                }
+            },
+            doc! {
+                "$project": {
+                    "users": 1,
+                    "total": { "$ifNull": [{ "$arrayElemAt": ["$total.count", 0] }, 0] },
+                }
+            },
+        ];
+        let pipeline = &pipeline;
+        let col = &self.col();
+        let document = retry_read(read_retry_attempts(), || async move {
+            self.track(Operation::Aggregate, col.aggregate(pipeline.clone(), None))
+                .await?
+                .try_next()
+                .await
+        })
+        .await?;
+        let page = match document {
+            Some(document) => from_document(document)?,
+            None => UserPage::default(),
+        };
+        Ok((page.users, page.total.max(0) as u64))
+    }
+
+    /// Sets missing timestamps on legacy users from the creation time embedded in their ObjectId.
+    ///
+    /// Users without `created_at` get it set to the generation time of their `_id`; `updated_at`
+    /// is set to the same value unless it is already present.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing an `UpdateResult` whose `modified_count` is the number of users
+    /// backfilled, or a `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if there is an issue with updating the users in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let result = repo.backfill_timestamps().await?;
+    /// println!("Backfilled {} users", result.modified_count);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn backfill_timestamps(&self) -> Result<UpdateResult, RepoError> {
+        let filter = doc! {"created_at": {"$exists": false}};
+        let pipeline = vec![doc! {
+            "$set": {
+                "created_at": { "$toDate": "$_id" },
+                "updated_at": { "$ifNull": ["$updated_at", { "$toDate": "$_id" }] },
+            }
+        }];
+        let col = self.col();
+        let update = col.update_many(filter, pipeline, None);
+        self.track(Operation::Update, update)
+            .await
+            .map_err(RepoError::from)
+    }
+
+    /// Retrieves the users that come after a given ID, in ascending `_id` order, asynchronously.
+    ///
+    /// Unlike offset pagination, each page is an index range scan on `_id`, so it stays fast on
+    /// large collections and does not shift when users are inserted.
+    ///
+    /// # Arguments
+    ///
+    /// * `after_id` - The last ID of the previous page, or `None` for the first page.
+    /// * `limit` - The maximum number of users to return.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing up to `limit` users with an `_id` greater than `after_id`, or a
+    /// `RepoError` if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if there is an issue with querying the database or mapping through the cursor.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let first = repo.get_users_after(None, 20).await?;
+    /// let next = repo.get_users_after(first.last().and_then(|user| user.id), 20).await?;
+    /// println!("Second page has {} users", next.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_users_after(
+        &self,
+        after_id: Option<ObjectId>,
+        limit: i64,
+    ) -> Result<Vec<User>, RepoError> {
+        let filter = after_id.map(|after_id| doc! {"_id": {"$gt": after_id}});
+        let mut options = FindOptions::builder()
+            .sort(doc! {"_id": 1})
+            .limit(limit)
+            .build();
+        options.batch_size = batch_size();
+        let col = &self.col();
+        let filter = &filter;
+        let options = &options;
+        let metrics = &self.metrics;
+        retry_read(read_retry_attempts(), || async move {
+            metrics
+                .track(Operation::Find, col.find(filter.clone(), options.clone()))
+                .await?
+                .try_collect()
+                .await
+        })
+        .await
+        .map_err(RepoError::from)
+    }
+
+    /// Streams every user from a single cursor.
+    ///
+    /// Users are fetched in batches of `MONGO_BATCH_SIZE` (or the driver default) as the stream is
+    /// polled, so memory use does not grow with the size of the collection.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the stream of users, or a `RepoError` if the query cannot be started.
+    /// Errors while reading later batches are yielded by the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RepoError::Database` if there is an issue with querying the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use futures::stream::TryStreamExt;
+    /// # use your_project_name::repository::{error::RepoError, YourRepository};
+    /// # async fn example_function(repo: &YourRepository) -> Result<(), RepoError> {
+    /// let mut users = repo.stream_users().await?;
+    /// while let Some(user) = users.try_next().await? {
+    ///     println!("User: {:?}", user);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stream_users(
+        &self,
+    ) -> Result<impl Stream<Item = Result<User, RepoError>> + 'static, RepoError> {
+        let col = self.col();
+        let options = page_options(0, 0, None);
+        let cursor = retry_read(read_retry_attempts(), || {
+            self.track(Operation::Find, col.find(None, options.clone()))
+        })
+        .await?;
+        Ok(cursor.map_err(RepoError::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::aggregate_model::TitleCount;
+    use mongodb::bson::oid::ObjectId;
+
+    #[tokio::test]
+    async fn test_create_user() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let new_user = User {
+            id: Some(ObjectId::new()),
+            name: String::from("Test User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.create_user(new_user.clone()).await;
+
+        // Assert
+        assert!(result.is_ok(), "Failed to create user: {:?}", result.err());
+        let inserted_user = result.unwrap();
+        assert_eq!(inserted_user.id, new_user.id);
+        assert!(inserted_user.member_number.is_some());
+        assert!(inserted_user.created_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_user() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = mongodb::bson::oid::ObjectId::new(); // Generate a new ObjectId
+
+        // Create a user before trying to retrieve it
+        let new_user = User {
+            id: Some(id),
+            name: "Expected Name".to_string(),
+            location: "Some Location".to_string(), // Add a location
+            title: "Some Title".to_string(),       // Add a title
+            ..Default::default()
+        };
+        let create_result = repo.create_user(new_user).await;
+        assert!(
+            create_result.is_ok(),
+            "Failed to create user: {:?}",
+            create_result.err()
+        );
+
+        // Act
+        let result = match repo.get_user(&id.to_string(), None).await {
+            Ok(Some(user)) => user,
+            Ok(None) => panic!("User {} not found", id),
+            Err(e) => panic!("Failed to get user: {:?}", e),
+        };
+
+        // Assert
+        assert_eq!(result.name, "Expected Name", "User name does not match");
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = String::from("some_id"); // Provide an existing user ID
+        let updated_user = User {
+            id: Some(ObjectId::new()), // Provide a new ID or the same ID
+            name: String::from("Updated Name"),
+            location: String::from("Updated Location"),
             title: String::from("Updated Title"),
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.update_user(&id, updated_user).await;
+
+        // Assert
+        assert!(result.is_ok(), "Failed to update user: {:?}", result.err());
+        let update_result = result.unwrap();
+        assert_eq!(update_result.modified_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sample_users() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        for i in 0..5 {
+            let new_user = User {
+                id: None,
+                name: format!("Sample User {}", i),
+                location: String::from("Sample Location"),
+                title: String::from("Sample Title"),
+                ..Default::default()
+            };
+            repo.create_user(new_user)
+                .await
+                .expect("Failed to seed user");
+        }
+        let total = repo.get_all_users(None).await.unwrap().len();
+        let n = 3;
+
+        // Act
+        let result = repo.sample_users(n as i64).await;
+
+        // Assert
+        assert!(result.is_ok(), "Failed to sample users: {:?}", result.err());
+        let sample = result.unwrap();
+        assert_eq!(sample.len(), n.min(total));
+        let ids: std::collections::HashSet<_> = sample.iter().map(|user| user.id).collect();
+        assert_eq!(ids.len(), sample.len(), "Sample contains duplicate users");
+    }
+
+    #[tokio::test]
+    async fn test_get_by_name_and_location() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let name = format!("Compound User {}", ObjectId::new());
+        let new_user = User {
+            id: None,
+            name: name.clone(),
+            location: String::from("Compound Location"),
+            title: String::from("Compound Title"),
+            ..Default::default()
+        };
+        repo.create_user(new_user)
+            .await
+            .expect("Failed to seed user");
+
+        // Act
+        let found = repo
+            .get_by_name_and_location(&name, "Compound Location")
+            .await
+            .expect("Failed to query user");
+        let mismatch = repo
+            .get_by_name_and_location(&name, "Other Location")
+            .await
+            .expect("Failed to query user");
+
+        // Assert
+        assert_eq!(found.map(|user| user.name), Some(name));
+        assert!(mismatch.is_none(), "Mismatched location should not match");
+    }
+
+    #[tokio::test]
+    async fn test_filter_options() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let marker = ObjectId::new().to_hex();
+        let location = format!("Facet Location {}", marker);
+        let title = format!("Facet Title {}", marker);
+        for i in 0..3 {
+            let new_user = User {
+                id: None,
+                name: format!("Facet User {}", i),
+                location: location.clone(),
+                title: title.clone(),
+                ..Default::default()
+            };
+            repo.create_user(new_user)
+                .await
+                .expect("Failed to seed user");
+        }
+
+        // Act
+        let result = repo.filter_options().await;
+
+        // Assert
+        assert!(
+            result.is_ok(),
+            "Failed to get filter options: {:?}",
+            result.err()
+        );
+        let options = result.unwrap();
+        assert_eq!(
+            options.locations.iter().filter(|l| **l == location).count(),
+            1
+        );
+        assert_eq!(options.titles.iter().filter(|t| **t == title).count(), 1);
+        assert!(options.locations.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(options.titles.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[tokio::test]
+    async fn test_dump_and_restore_round_trip() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let new_user = User {
+            id: Some(ObjectId::new()),
+            name: String::from("Dumped User"),
+            location: String::from("Dump Location"),
+            title: String::from("Dump Title"),
+            ..Default::default()
+        };
+        let id = new_user.id.unwrap();
+        repo.create_user(new_user)
+            .await
+            .expect("Failed to seed user");
+        let dump = repo.dump().await.expect("Failed to dump users");
+        repo.delete_users_by_ids(&[id])
+            .await
+            .expect("Failed to delete user");
+
+        // Act
+        let result = repo.restore(&dump).await;
+
+        // Assert
+        assert!(result.is_ok(), "Failed to restore dump: {:?}", result.err());
+        assert!(result.unwrap() >= 1);
+        let restored = repo.get_user(&id.to_hex(), None).await.unwrap().unwrap();
+        assert_eq!(restored.name, "Dumped User");
+        assert_eq!(restored.location, "Dump Location");
+        assert_eq!(restored.title, "Dump Title");
+    }
+
+    #[tokio::test]
+    async fn test_title_distribution_by_location() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let marker = ObjectId::new().to_hex();
+        let north = format!("North {}", marker);
+        let south = format!("South {}", marker);
+        let matrix = [
+            (&north, "Engineer"),
+            (&north, "Engineer"),
+            (&north, "Manager"),
+            (&south, "Engineer"),
+        ];
+        for (location, title) in matrix {
+            let new_user = User {
+                id: None,
+                name: String::from("Matrix User"),
+                location: location.clone(),
+                title: String::from(title),
+                ..Default::default()
+            };
+            repo.create_user(new_user)
+                .await
+                .expect("Failed to seed user");
+        }
+
+        // Act
+        let result = repo.title_distribution_by_location().await;
+
+        // Assert
+        assert!(
+            result.is_ok(),
+            "Failed to compute distribution: {:?}",
+            result.err()
+        );
+        let distribution = result.unwrap();
+        let north_counts = distribution.iter().find(|l| l.location == north).unwrap();
+        let south_counts = distribution.iter().find(|l| l.location == south).unwrap();
+        assert_eq!(north_counts.total, 3);
+        assert_eq!(
+            north_counts.titles,
+            vec![
+                TitleCount {
+                    title: String::from("Engineer"),
+                    count: 2
+                },
+                TitleCount {
+                    title: String::from("Manager"),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(south_counts.total, 1);
+        assert_eq!(
+            south_counts.titles,
+            vec![TitleCount {
+                title: String::from("Engineer"),
+                count: 1
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_active_since() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let touched = ObjectId::new();
+        let untouched = ObjectId::new();
+        for id in [touched, untouched] {
+            let new_user = User {
+                id: Some(id),
+                name: String::from("Active User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            };
+            repo.create_user(new_user)
+                .await
+                .expect("Failed to seed user");
+        }
+        repo.touch_user(&touched)
+            .await
+            .expect("Failed to touch user");
+        let since = DateTime::from_millis(DateTime::now().timestamp_millis() - 15 * 60 * 1000);
+
+        // Act
+        let result = repo.active_since(since).await;
+
+        // Assert
+        assert!(
+            result.is_ok(),
+            "Failed to get active users: {:?}",
+            result.err()
+        );
+        let ids: Vec<_> = result
+            .unwrap()
+            .into_iter()
+            .filter_map(|user| user.id)
+            .collect();
+        assert!(ids.contains(&touched), "Touched user should be active");
+        assert!(
+            !ids.contains(&untouched),
+            "Untouched user should not be active"
+        );
+    }
+
+    /// Rewrites the database path of a MongoDB connection string.
+    fn with_database(uri: &str, db_name: &str) -> String {
+        let (base, query) = match uri.split_once('?') {
+            Some((base, query)) => (base, Some(query)),
+            None => (uri, None),
+        };
+        let hosts_start = base.find("://").map_or(0, |index| index + 3);
+        let hosts_end = base[hosts_start..]
+            .find('/')
+            .map_or(base.len(), |index| hosts_start + index);
+        match query {
+            Some(query) => format!("{}/{}?{}", &base[..hosts_end], db_name, query),
+            None => format!("{}/{}", &base[..hosts_end], db_name),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_targets_new_database() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let uri = env::var("MONGOURI").unwrap();
+        let new_db = format!("rustDB_reconnect_{}", ObjectId::new().to_hex());
+        let id = ObjectId::new();
+
+        // Act
+        repo.reconnect(&with_database(&uri, &new_db))
+            .await
+            .expect("Failed to reconnect");
+        let new_user = User {
+            id: Some(id),
+            name: String::from("Reconnected User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        repo.create_user(new_user)
+            .await
+            .expect("Failed to create user");
+
+        // Assert
+        assert_eq!(repo.database_name(), new_db);
+        let client = Client::with_uri_str(&uri).await.unwrap();
+        let stored = client
+            .database(&new_db)
+            .collection::<User>("User")
+            .find_one(doc! {"_id": id}, None)
+            .await
+            .unwrap();
+        assert!(stored.is_some(), "User was not written to the new database");
+        client.database(&new_db).drop(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_claim_user() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = ObjectId::new();
+        let new_user = User {
+            id: Some(id),
+            name: String::from("Claimed User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        repo.create_user(new_user)
+            .await
+            .expect("Failed to seed user");
+        let ttl = Duration::from_millis(500);
+
+        // Act
+        let first = repo.claim_user(&id, "worker-1", ttl).await.unwrap();
+        let second = repo.claim_user(&id, "worker-2", ttl).await.unwrap();
+        tokio::time::sleep(ttl + Duration::from_millis(100)).await;
+        let after_expiry = repo.claim_user(&id, "worker-2", ttl).await.unwrap();
+        let released = repo.release_user(&id, "worker-2").await.unwrap();
+
+        // Assert
+        assert_eq!(
+            first.and_then(|user| user.locked_by).as_deref(),
+            Some("worker-1")
+        );
+        assert!(second.is_none(), "A held claim should not be taken over");
+        assert_eq!(
+            after_expiry.and_then(|user| user.locked_by).as_deref(),
+            Some("worker-2")
+        );
+        assert_eq!(released.matched_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_scroll_users_resumes_without_gaps_or_repeats() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        for i in 0..4 {
+            let new_user = User {
+                id: None,
+                name: format!("Scroll User {}", i),
+                location: String::from("Scroll Location"),
+                title: String::from("Scroll Title"),
+                ..Default::default()
+            };
+            repo.create_user(new_user)
+                .await
+                .expect("Failed to seed user");
+        }
+
+        // Act
+        let first = repo.scroll_users(None, 2).await.unwrap().unwrap();
+        let scroll_id = ObjectId::parse_str(&first.scroll_id).unwrap();
+        let second = repo
+            .scroll_users(Some(scroll_id), 2)
+            .await
+            .unwrap()
+            .unwrap();
+        let unknown = repo.scroll_users(Some(ObjectId::new()), 2).await.unwrap();
+
+        // Assert
+        assert_eq!(first.users.len(), 2);
+        assert_eq!(second.scroll_id, first.scroll_id);
+        let first_ids: Vec<_> = first.users.iter().filter_map(|user| user.id).collect();
+        let second_ids: Vec<_> = second.users.iter().filter_map(|user| user.id).collect();
+        let next_id = repo
+            .col()
+            .find_one(
+                doc! {"_id": {"$gt": first_ids[1]}},
+                FindOneOptions::builder().sort(doc! {"_id": 1}).build(),
+            )
+            .await
+            .unwrap()
+            .and_then(|user| user.id);
+        assert!(first_ids[0] < first_ids[1]);
+        assert_eq!(second_ids.first().copied(), next_id, "Scroll skipped users");
+        assert!(
+            second_ids.iter().all(|id| !first_ids.contains(id)),
+            "Scroll repeated users"
+        );
+        assert!(unknown.is_none(), "Unknown scroll IDs should not resolve");
+    }
+
+    #[test]
+    fn test_content_hash_changes_with_email() {
+        // Arrange
+        let user = User {
+            name: String::from("Hash User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            email: String::from("hash@example.com"),
+            ..Default::default()
+        };
+        let moved = User {
+            email: String::from("moved@example.com"),
+            ..user.clone()
+        };
+
+        // Act
+        let original = user.content_hash();
+        let changed = moved.content_hash();
+
+        // Assert
+        assert_ne!(changed, original);
+    }
+
+    #[tokio::test]
+    async fn test_content_hash_ignores_last_seen() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = ObjectId::new();
+        let user = User {
+            id: Some(id),
+            name: String::from("Hash User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+        repo.create_user(user.clone())
+            .await
+            .expect("Failed to seed user");
+        let original = repo.content_hash(&id).await.unwrap();
+
+        // Act
+        repo.touch_user(&id).await.unwrap();
+        let touched = repo.content_hash(&id).await.unwrap();
+        let retitled = User {
+            title: String::from("Other Title"),
+            ..user
+        };
+        repo.update_user(&id.to_hex(), retitled).await.unwrap();
+        let updated = repo.content_hash(&id).await.unwrap();
+
+        // Assert
+        assert!(original.is_some());
+        assert_eq!(
+            touched, original,
+            "Touching a user should not change its hash"
+        );
+        assert_ne!(
+            updated, original,
+            "Changing the title should change the hash"
+        );
+    }
+
+    #[test]
+    fn test_batch_size_is_applied_to_page_options() {
+        // Act
+        let options = page_options(0, 0, None);
+
+        // Assert
+        assert_eq!(parse_batch_size(Some("500")), Some(500));
+        assert_eq!(parse_batch_size(Some("0")), None);
+        assert_eq!(parse_batch_size(Some("lots")), None);
+        assert_eq!(parse_batch_size(None), None);
+        assert_eq!(options.batch_size, batch_size());
+    }
+
+    #[test]
+    fn test_apply_pool_settings() {
+        // Arrange
+        let mut configured = ClientOptions::builder().build();
+        let mut invalid = ClientOptions::builder().build();
+        let settings = |name: &str| match name {
+            "MONGO_MAX_POOL" => Some(String::from("50")),
+            "MONGO_MIN_POOL" => Some(String::from(" 5 ")),
+            "MONGO_CONNECT_TIMEOUT_MS" => Some(String::from("2500")),
+            _ => None,
+        };
+
+        // Act
+        apply_pool_settings(&mut configured, settings);
+        apply_pool_settings(&mut invalid, |_| Some(String::from("many")));
+
+        // Assert
+        assert_eq!(configured.max_pool_size, Some(50));
+        assert_eq!(configured.min_pool_size, Some(5));
+        assert_eq!(
+            configured.connect_timeout,
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(invalid.max_pool_size, None);
+        assert_eq!(invalid.min_pool_size, None);
+        assert_eq!(invalid.connect_timeout, None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_read_recovers_from_transient_error() {
+        // Arrange
+        let mut calls = 0;
+
+        // Act
+        let result = retry_read(3, || {
+            calls += 1;
+            let outcome = if calls == 1 {
+                Err(ErrorKind::from(std::io::ErrorKind::ConnectionReset).into())
+            } else {
+                Ok("read")
+            };
+            async move { outcome }
+        })
+        .await;
+
+        // Assert
+        assert_eq!(result.unwrap(), "read");
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_read_does_not_retry_query_errors() {
+        // Arrange
+        let mut calls = 0;
+
+        // Act
+        let result: mongodb::error::Result<()> = retry_read(3, || {
+            calls += 1;
+            async { Err(mongodb::error::Error::custom("bad query")) }
+        })
+        .await;
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_users_paginated() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        for index in 0..3 {
+            repo.create_user(User {
+                name: format!("Paged User {}", index),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+
+        // Act
+        let page = repo.get_users_paginated(1, 2, None).await.unwrap();
+        let unlimited = repo.get_users_paginated(0, 0, None).await.unwrap();
+        let negative = repo.get_users_paginated(0, -1, None).await.unwrap();
+
+        // Assert
+        assert_eq!(page.len(), 2);
+        assert!(unlimited.len() >= 3);
+        assert_eq!(negative.len(), unlimited.len());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_operations() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let before = repo.metrics();
+        let new_user = User {
+            name: String::from("Metered User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+
+        // Act
+        let inserted = repo.create_user(new_user).await.unwrap();
+        let id = inserted.id.unwrap();
+        repo.get_user(&id.to_hex(), None).await.unwrap();
+        let after = repo.metrics();
+
+        // Assert
+        assert_eq!(after.inserts, before.inserts + 1);
+        assert_eq!(after.finds, before.finds + 1);
+        assert_eq!(after.errors, before.errors);
+        assert_eq!(after.in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_users_sorted() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        for name in ["Sorted Charlie", "Sorted Alice", "Sorted Bob"] {
+            repo.create_user(User {
+                name: String::from(name),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+
+        // Act
+        let ascending = repo.get_all_users_sorted("name", true, None).await.unwrap();
+        let descending = repo
+            .get_all_users_sorted("name", false, None)
+            .await
+            .unwrap();
+        let unknown = repo.get_all_users_sorted("password", true, None).await;
+
+        // Assert
+        assert!(ascending
+            .windows(2)
+            .all(|pair| pair[0].name <= pair[1].name));
+        assert!(descending
+            .windows(2)
+            .all(|pair| pair[0].name >= pair[1].name));
+        assert!(matches!(
+            unknown,
+            Err(RepoError::InvalidSortField(field)) if field == "password"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search_users_by_name() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let marker = ObjectId::new().to_hex();
+        for name in [
+            format!("Search {} a.b", marker),
+            format!("SEARCH {} AXB", marker),
+        ] {
+            repo.create_user(User {
+                name,
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+
+        // Act
+        let case_insensitive = repo
+            .search_users_by_name(&format!("search {}", marker))
+            .await
+            .unwrap();
+        let literal = repo
+            .search_users_by_name(&format!("{} a.b", marker))
+            .await
+            .unwrap();
+        let none = repo
+            .search_users_by_name(&format!("{} missing", marker))
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(case_insensitive.len(), 2);
+        assert_eq!(literal.len(), 1, "'.' should not match any character");
+        assert!(none.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_users_by_location() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let location = format!("Location {}", ObjectId::new().to_hex());
+        for name in ["Local One", "Local Two"] {
+            repo.create_user(User {
+                name: String::from(name),
+                location: location.clone(),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+
+        // Act
+        let users = repo.get_users_by_location(&location).await.unwrap();
+        let empty = repo.get_users_by_location("").await;
+
+        // Assert
+        assert_eq!(users.len(), 2);
+        assert!(users.iter().all(|user| user.location == location));
+        assert!(matches!(empty, Err(RepoError::EmptyLocation)));
+    }
+
+    #[tokio::test]
+    async fn test_count_users() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let location = format!("Location {}", ObjectId::new().to_hex());
+        for name in ["Counted One", "Counted Two"] {
+            repo.create_user(User {
+                name: String::from(name),
+                location: location.clone(),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+
+        // Act
+        let in_location = repo.count_users(Some(&location)).await.unwrap();
+        let total = repo.count_users(None).await.unwrap();
+
+        // Assert
+        assert_eq!(in_location, 2);
+        assert!(total >= in_location);
+    }
+
+    #[tokio::test]
+    async fn test_get_with_colleagues() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let location = format!("Location {}", ObjectId::new().to_hex());
+        let ids: Vec<ObjectId> = (0..4).map(|_| ObjectId::new()).collect();
+        for id in &ids {
+            repo.create_user(User {
+                id: Some(*id),
+                name: String::from("Colleague"),
+                location: location.clone(),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+
+        // Act
+        let (user, colleagues) = repo
+            .get_with_colleagues(&ids[0], 2)
+            .await
+            .unwrap()
+            .expect("Seeded user missing");
+        let missing = repo.get_with_colleagues(&ObjectId::new(), 2).await.unwrap();
+
+        // Assert
+        assert_eq!(user.id, Some(ids[0]));
+        assert_eq!(colleagues.len(), 2);
+        assert!(colleagues
+            .iter()
+            .all(|colleague| colleague.id != Some(ids[0])));
+        assert!(colleagues
+            .iter()
+            .all(|colleague| colleague.location == location));
+        assert!(missing.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_id_is_rejected() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = String::from("abc");
+
+        // Act
+        let get = repo.get_user(&id, None).await;
+        let update = repo.update_user(&id, User::default()).await;
+        let delete = repo.delete_user(&id).await;
+
+        // Assert
+        assert!(matches!(get, Err(RepoError::InvalidObjectId(_))));
+        assert!(matches!(update, Err(RepoError::InvalidObjectId(_))));
+        assert!(matches!(delete, Err(RepoError::InvalidObjectId(_))));
+    }
+
+    #[tokio::test]
+    async fn test_missing_user_is_not_found() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = ObjectId::new().to_hex();
+
+        // Act
+        let update = repo.update_user(&id, User::default()).await;
+        let delete = repo.delete_user(&id).await;
+
+        // Assert
+        assert!(matches!(update, Err(RepoError::NotFound)));
+        assert!(matches!(delete, Err(RepoError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_patch_user() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = ObjectId::new();
+        repo.create_user(User {
+            id: Some(id),
+            name: String::from("Patched User"),
+            location: String::from("Patch Location"),
+            title: String::from("Patch Title"),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to seed user");
+        let patch = UserPatch {
+            title: Some(String::from("New Title")),
+            ..Default::default()
         };
 
         // Act
-        let result = repo.update_user(&id, updated_user).await;
+        let patched = repo.patch_user(&id.to_hex(), patch).await.unwrap();
+        let empty = repo.patch_user(&id.to_hex(), UserPatch::default()).await;
 
         // Assert
-        assert!(result.is_ok(), "Failed to update user: {:?}", result.err());
-        let update_result = result.unwrap();
-        assert_eq!(update_result.modified_count, 1);
+        assert_eq!(patched.title, "New Title");
+        assert_eq!(patched.name, "Patched User");
+        assert_eq!(patched.location, "Patch Location");
+        assert!(matches!(empty, Err(RepoError::EmptyPatch)));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_does_not_store_id_field() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = ObjectId::new();
+        repo.create_user(User {
+            id: Some(id),
+            name: String::from("Stray Id User"),
+            location: String::from("Stray Id Location"),
+            title: String::from("Stray Id Title"),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to seed user");
+        let updated_user = User {
+            id: Some(id),
+            name: String::from("Stray Id User Updated"),
+            location: String::from("Stray Id Location"),
+            title: String::from("Stray Id Title"),
+            ..Default::default()
+        };
+
+        // Act
+        repo.update_user(&id.to_hex(), updated_user).await.unwrap();
+        let stored = repo
+            .col()
+            .clone_with_type::<Document>()
+            .find_one(doc! {"_id": id}, None)
+            .await
+            .unwrap()
+            .expect("Updated user should exist");
+
+        // Assert
+        assert!(!stored.contains_key("id"));
+        assert_eq!(stored.get_str("name").unwrap(), "Stray Id User Updated");
+    }
+
+    #[tokio::test]
+    async fn test_create_many_users() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let taken = ObjectId::new();
+        let users = (0..3)
+            .map(|i| User {
+                id: Some(taken),
+                name: format!("Bulk User {}", i),
+                location: String::from("Bulk Location"),
+                title: String::from("Bulk Title"),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        // Act
+        let results = repo.create_many_users(users).await.unwrap();
+        let empty = repo.create_many_users(Vec::new()).await;
+
+        // Assert
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .iter()
+            .all(|result| matches!(result, Ok(id) if *id != taken)));
+        assert!(matches!(empty, Err(RepoError::EmptyBatch)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_users_by_location() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let location = format!("Throwaway {}", ObjectId::new().to_hex());
+        for i in 0..2 {
+            repo.create_user(User {
+                id: None,
+                name: format!("Throwaway User {}", i),
+                location: location.clone(),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+
+        // Act
+        let result = repo.delete_users_by_location(&location).await.unwrap();
+        let remaining = repo.get_users_by_location(&location).await.unwrap();
+        let blank = repo.delete_users_by_location(" ").await;
+
+        // Assert
+        assert_eq!(result.len(), 2);
+        assert!(remaining.is_empty());
+        assert!(matches!(blank, Err(RepoError::EmptyLocation)));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_email_is_rejected() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let email = format!("{}@example.com", ObjectId::new().to_hex());
+        let user = |name: &str| User {
+            id: None,
+            name: String::from(name),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            email: email.clone(),
+            ..Default::default()
+        };
+        repo.create_user(user("First Email User"))
+            .await
+            .expect("Failed to seed user");
+
+        // Act
+        let duplicate = repo.create_user(user("Second Email User")).await;
+        let without_email = repo
+            .create_user(User {
+                email: String::new(),
+                ..user("Emailless User")
+            })
+            .await;
+
+        // Assert
+        assert!(matches!(duplicate, Err(RepoError::DuplicateEmail)));
+        assert!(without_email.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_find_with_count() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let location = format!("Counted {}", ObjectId::new().to_hex());
+        for i in 0..5 {
+            repo.create_user(User {
+                id: None,
+                name: format!("Counted User {}", i),
+                location: location.clone(),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+        }
+        let options = FindOptions::builder()
+            .sort(doc! {"name": 1})
+            .limit(2)
+            .build();
+
+        // Act
+        let (users, total) = repo
+            .find_with_count(doc! {"location": &location}, options)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(total, 5);
+        assert_eq!(
+            users
+                .iter()
+                .map(|user| user.name.as_str())
+                .collect::<Vec<_>>(),
+            ["Counted User 0", "Counted User 1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timestamps_are_set_by_the_repository() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let forged = DateTime::from_millis(0);
+        let before = DateTime::now();
+        let result = repo
+            .create_user(User {
+                id: None,
+                name: String::from("Timestamped User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                created_at: Some(forged),
+                updated_at: Some(forged),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let id = result.id.unwrap().to_hex();
+
+        // Act
+        let created = repo.get_user(&id, None).await.unwrap().unwrap();
+        let patched = repo
+            .patch_user(
+                &id,
+                UserPatch {
+                    title: Some(String::from("Patched Title")),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // Assert
+        let created_at = created.created_at.unwrap();
+        assert!(created_at >= before);
+        assert_eq!(created.updated_at, Some(created_at));
+        assert_eq!(patched.created_at, Some(created_at));
+        assert!(patched.updated_at.unwrap() >= created_at);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_timestamps() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let id = ObjectId::new();
+        repo.col()
+            .clone_with_type::<Document>()
+            .insert_one(
+                doc! {
+                    "_id": id,
+                    "name": "Legacy User",
+                    "location": "Test Location",
+                    "title": "Test Title",
+                },
+                None,
+            )
+            .await
+            .expect("Failed to seed legacy user");
+
+        // Act
+        let result = repo.backfill_timestamps().await.unwrap();
+        let user = repo.get_user(&id.to_hex(), None).await.unwrap().unwrap();
+
+        // Assert
+        assert!(result.modified_count >= 1);
+        assert_eq!(user.created_at, Some(id.timestamp()));
+        assert_eq!(user.updated_at, Some(id.timestamp()));
+    }
+
+    #[tokio::test]
+    async fn test_init_with_names_uses_isolated_collection() {
+        // Arrange
+        let collection = format!("User_isolated_{}", ObjectId::new().to_hex());
+        let repo = MongoRepo::init_with_names(DEFAULT_DB_NAME, &collection)
+            .await
+            .unwrap();
+        let id = ObjectId::new();
+
+        // Act
+        repo.create_user(User {
+            id: Some(id),
+            name: String::from("Isolated User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create user");
+
+        // Assert
+        let users = repo.get_all_users(None).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, Some(id));
+        repo.col().drop(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ping() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+
+        // Act
+        let result = repo.ping().await;
+
+        // Assert
+        assert!(result.is_ok(), "Ping failed: {:?}", result.err());
+    }
+
+    #[tokio::test]
+    async fn test_member_numbers_are_sequential() {
+        // Arrange
+        let collection = format!("User_members_{}", ObjectId::new().to_hex());
+        let repo = MongoRepo::init_with_names(DEFAULT_DB_NAME, &collection)
+            .await
+            .unwrap();
+        let user = |name: &str| User {
+            id: None,
+            name: String::from(name),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        };
+
+        // Act
+        let (first, second, third) = futures::join!(
+            repo.create_user(user("Member One")),
+            repo.create_user(user("Member Two")),
+            repo.create_user(user("Member Three")),
+        );
+
+        // Assert
+        assert!(first.is_ok() && second.is_ok() && third.is_ok());
+        let mut numbers = repo
+            .get_all_users(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|user| user.member_number)
+            .collect::<Vec<_>>();
+        numbers.sort();
+        assert_eq!(numbers, [1, 2, 3]);
+        repo.col().drop(None).await.unwrap();
+        repo.counters_col()
+            .delete_one(doc! {"_id": &collection}, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_users_after() {
+        // Arrange
+        let collection = format!("User_cursor_{}", ObjectId::new().to_hex());
+        let repo = MongoRepo::init_with_names(DEFAULT_DB_NAME, &collection)
+            .await
+            .unwrap();
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let id = ObjectId::new();
+            repo.create_user(User {
+                id: Some(id),
+                name: format!("Cursor User {}", i),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to seed user");
+            ids.push(id);
+        }
+
+        // Act
+        let first = repo.get_users_after(None, 2).await.unwrap();
+        let second = repo.get_users_after(first[1].id, 2).await.unwrap();
+        let last = repo.get_users_after(Some(ids[4]), 2).await.unwrap();
+
+        // Assert
+        let page_ids = |users: &[User]| users.iter().filter_map(|user| user.id).collect::<Vec<_>>();
+        assert_eq!(page_ids(&first), ids[..2]);
+        assert_eq!(page_ids(&second), ids[2..4]);
+        assert!(last.is_empty());
+        repo.col().drop(None).await.unwrap();
+    }
+
+    #[derive(Debug, Serialize, serde::Deserialize, PartialEq)]
+    struct Product {
+        #[serde(rename = "_id")]
+        id: ObjectId,
+        name: String,
+        price: i64,
+    }
+
+    #[tokio::test]
+    async fn test_repository_handles_other_models() {
+        // Arrange
+        let collection = format!("Product_{}", ObjectId::new().to_hex());
+        let client = Client::with_uri_str(env::var("MONGOURI").unwrap())
+            .await
+            .unwrap();
+        let repo = MongoRepo::<Product>::with_client(client, DEFAULT_DB_NAME, &collection);
+        let id = ObjectId::new();
+        let product = Product {
+            id,
+            name: String::from("Widget"),
+            price: 10,
+        };
+
+        // Act
+        repo.create(product)
+            .await
+            .expect("Failed to create product");
+        repo.update(
+            &id.to_hex(),
+            Product {
+                id,
+                name: String::from("Widget"),
+                price: 12,
+            },
+        )
+        .await
+        .expect("Failed to update product");
+        let found = repo.find_by_id(&id.to_hex()).await.unwrap();
+        let listed = repo.list().await.unwrap();
+        repo.delete(&id.to_hex())
+            .await
+            .expect("Failed to delete product");
+        let missing = repo.delete(&id.to_hex()).await;
+
+        // Assert
+        assert_eq!(found.map(|product| product.price), Some(12));
+        assert_eq!(listed.len(), 1);
+        assert!(matches!(missing, Err(RepoError::NotFound)));
+        repo.col().drop(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_last_modified_advances_after_write() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        repo.create_user(User {
+            name: String::from("Last Modified User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to seed user");
+        let before = repo.last_modified().await.unwrap();
+        let finds = repo.metrics().finds;
+        let cached = repo.last_modified().await.unwrap();
+        let finds_after_cached_read = repo.metrics().finds;
+
+        // Act
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let inserted = repo
+            .create_user(User {
+                name: String::from("Later User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to create user");
+        let after = repo.last_modified().await.unwrap();
+
+        // Assert
+        assert_eq!(cached, before);
+        assert_eq!(finds_after_cached_read, finds);
+        let id = inserted.id.unwrap();
+        let latest = repo.get_user(&id.to_hex(), None).await.unwrap();
+        assert!(after > before);
+        assert_eq!(after, latest.and_then(|user| user.updated_at));
+    }
+
+    #[tokio::test]
+    async fn test_repositories_share_one_client() {
+        // Arrange
+        let client = Client::with_uri_str(env::var("MONGOURI").unwrap())
+            .await
+            .unwrap();
+        let collection = format!("Product_{}", ObjectId::new().to_hex());
+        let users = MongoRepo::from_client(client.clone(), DEFAULT_DB_NAME);
+        let products = MongoRepo::<Product>::with_client(client, DEFAULT_DB_NAME, &collection);
+        let id = ObjectId::new();
+
+        // Act
+        let user = users
+            .create_user(User {
+                name: String::from("Shared Client User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await;
+        let product = products
+            .create(Product {
+                id,
+                name: String::from("Gadget"),
+                price: 5,
+            })
+            .await;
+
+        // Assert
+        assert!(user.is_ok());
+        assert!(product.is_ok());
+        assert!(products.find_by_id(&id.to_hex()).await.unwrap().is_some());
+        products.col().drop(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_users_projected_returns_only_requested_fields() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        repo.create_user(User {
+            name: String::from("Projected User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to seed user");
+
+        // Act
+        let users = repo.list_users_projected(&["name"], None).await.unwrap();
+
+        // Assert
+        assert!(!users.is_empty());
+        assert!(users.iter().all(|user| {
+            let mut keys = user.keys().map(String::as_str).collect::<Vec<_>>();
+            keys.sort();
+            keys == ["_id", "name"]
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_user_by_email() {
+        // Arrange
+        // A collection of its own, so no other test advances its member number sequence.
+        let collection = format!("User_upserts_{}", ObjectId::new().to_hex());
+        let repo = MongoRepo::init_with_names(DEFAULT_DB_NAME, &collection)
+            .await
+            .unwrap();
+        let email = format!("{}@example.com", ObjectId::new().to_hex());
+        let user = User {
+            name: String::from("Synced User"),
+            location: String::from("Test Location"),
+            title: String::from("Test Title"),
+            email: email.clone(),
+            ..Default::default()
+        };
+
+        let counter = || async {
+            repo.counters_col()
+                .find_one(doc! {"_id": &repo.collection_name}, None)
+                .await
+                .unwrap()
+                .and_then(|counter| counter.get_i64("seq").ok())
+        };
+
+        // Act
+        let inserted = repo.upsert_user_by_email(&user).await.unwrap();
+        let created = repo.get_user_by_email(&email).await.unwrap().unwrap();
+        let seq_after_insert = counter().await;
+        let updated = repo
+            .upsert_user_by_email(&User {
+                title: String::from("Promoted Title"),
+                ..user
+            })
+            .await
+            .unwrap();
+        let stored = repo.get_user_by_email(&email).await.unwrap().unwrap();
+        let seq_after_update = counter().await;
+        let empty = repo.upsert_user_by_email(&User::default()).await;
+
+        // Assert
+        assert!(inserted.upserted_id.is_some());
+        assert!(created.member_number.is_some());
+        assert!(updated.upserted_id.is_none());
+        assert_eq!(updated.matched_count, 1);
+        assert_eq!(stored.id, created.id);
+        assert_eq!(stored.title, "Promoted Title");
+        assert_eq!(stored.member_number, created.member_number);
+        assert_eq!(stored.created_at, created.created_at);
+        assert_eq!(seq_after_update, seq_after_insert);
+        assert!(matches!(empty, Err(RepoError::EmptyEmail)));
+        repo.col().drop(None).await.unwrap();
+        repo.counters_col()
+            .delete_one(doc! {"_id": &collection}, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_user_without_email_keeps_stored_email() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let email = format!("{}@example.com", ObjectId::new().to_hex());
+        let created = repo
+            .create_user(User {
+                name: String::from("Emailed User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                email: email.clone(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let id = created.id.unwrap().to_hex();
+
+        // Act
+        repo.update_user(
+            &id,
+            User {
+                name: String::from("Emailed User"),
+                location: String::from("Updated Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let stored = repo.get_user(&id, None).await.unwrap().unwrap();
+
+        // Assert
+        assert_eq!(stored.email, email);
+        assert_eq!(stored.location, "Updated Location");
+    }
+
+    #[tokio::test]
+    async fn test_set_preferences_bumps_updated_at() {
+        // Arrange
+        let repo = MongoRepo::init().await.unwrap();
+        let created = repo
+            .create_user(User {
+                name: String::from("Preferences User"),
+                location: String::from("Test Location"),
+                title: String::from("Test Title"),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let id = created.id.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        // Act
+        repo.set_preferences(&id, doc! {"theme": "dark"})
+            .await
+            .unwrap();
+        let stored = repo.get_user(&id.to_hex(), None).await.unwrap().unwrap();
+
+        // Assert
+        assert!(stored.updated_at > created.updated_at);
     }
 }
\ No newline at end of file
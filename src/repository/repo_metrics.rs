@@ -0,0 +1,90 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The kinds of operation the repository sends to MongoDB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Insert,
+    Find,
+    Update,
+    Delete,
+    Aggregate,
+}
+
+/// Counters describing the database operations a repository has performed.
+///
+/// Counters only ever grow, except `in_flight`, which tracks operations that have started but
+/// not yet completed.
+#[derive(Debug, Default)]
+pub struct RepoMetrics {
+    inserts: AtomicU64,
+    finds: AtomicU64,
+    updates: AtomicU64,
+    deletes: AtomicU64,
+    aggregates: AtomicU64,
+    errors: AtomicU64,
+    in_flight: AtomicU64,
+}
+
+/// A point-in-time copy of `RepoMetrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoMetricsSnapshot {
+    pub inserts: u64,
+    pub finds: u64,
+    pub updates: u64,
+    pub deletes: u64,
+    pub aggregates: u64,
+    pub errors: u64,
+    pub in_flight: u64,
+}
+
+/// Decrements the in-flight counter when dropped, so cancelled operations are not left counted.
+struct InFlight<'a>(&'a AtomicU64);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl RepoMetrics {
+    /// Awaits `operation`, counting it under `kind` and counting it as an error if it fails.
+    pub async fn track<T, E>(
+        &self,
+        kind: Operation,
+        operation: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let counter = match kind {
+            Operation::Insert => &self.inserts,
+            Operation::Find => &self.finds,
+            Operation::Update => &self.updates,
+            Operation::Delete => &self.deletes,
+            Operation::Aggregate => &self.aggregates,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let _in_flight = InFlight(&self.in_flight);
+        let result = operation.await;
+        if result.is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Copies the current counter values.
+    pub fn snapshot(&self) -> RepoMetricsSnapshot {
+        RepoMetricsSnapshot {
+            inserts: self.inserts.load(Ordering::Relaxed),
+            finds: self.finds.load(Ordering::Relaxed),
+            updates: self.updates.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            aggregates: self.aggregates.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+        }
+    }
+}
\ No newline at end of file